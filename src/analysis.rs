@@ -0,0 +1,309 @@
+use crate::{
+    card_getter::CardGetter,
+    mtga_id_translator::{MtgaIdTranslator, NetCardData},
+    Color, Deck, Zone,
+};
+use indicatif::ProgressBar;
+use itertools::Itertools;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+/// Mana values from this one on share the last column of the curve
+const CURVE_TOP: u8 = 7;
+
+/// Card data of every card of the deck, from the translator cache, fetching
+/// the cards it does not know as `Collection::ensure_known` does
+pub fn card_data_of(
+    deck: &Deck,
+    translator: &mut MtgaIdTranslator,
+) -> HashMap<String, NetCardData> {
+    let names = deck.card_names().unique().cloned().collect_vec();
+    let pb = ProgressBar::new(names.len() as u64);
+    let mut card_data = HashMap::new();
+    for name in pb.wrap_iter(names.into_iter()) {
+        match CardGetter::card_data(translator, &name) {
+            Ok(data) => {
+                card_data.insert(name, data);
+            }
+            Err(err) => pb.println(format!("Failed to fetch unknown card: {name}. {err}")),
+        }
+    }
+    pb.finish_and_clear();
+    card_data
+}
+
+/// The type a card is counted as, the first of its types in this order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CardType {
+    Land,
+    Creature,
+    Planeswalker,
+    Instant,
+    Sorcery,
+    Artifact,
+    Enchantment,
+    Battle,
+    Other,
+}
+
+impl CardType {
+    /// Reads the front face of the type line
+    #[must_use]
+    pub fn of(type_line: &str) -> Self {
+        let front = type_line.split(" // ").next().unwrap_or_default();
+        let types = front.split(" — ").next().unwrap_or_default();
+        [
+            ("Land", Self::Land),
+            ("Creature", Self::Creature),
+            ("Planeswalker", Self::Planeswalker),
+            ("Instant", Self::Instant),
+            ("Sorcery", Self::Sorcery),
+            ("Artifact", Self::Artifact),
+            ("Enchantment", Self::Enchantment),
+            ("Battle", Self::Battle),
+        ]
+        .into_iter()
+        .find(|(name, _)| types.split_whitespace().any(|word| word == *name))
+        .map_or(Self::Other, |(_, card_type)| card_type)
+    }
+}
+
+impl fmt::Display for CardType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Land => write!(f, "lands"),
+            Self::Creature => write!(f, "creatures"),
+            Self::Planeswalker => write!(f, "planeswalkers"),
+            Self::Instant => write!(f, "instants"),
+            Self::Sorcery => write!(f, "sorceries"),
+            Self::Artifact => write!(f, "artifacts"),
+            Self::Enchantment => write!(f, "enchantments"),
+            Self::Battle => write!(f, "battles"),
+            Self::Other => write!(f, "others"),
+        }
+    }
+}
+
+/// Colored mana symbols of a mana cost. Hybrid symbols count for each of
+/// their colors. Only the front face of split, adventure and double-faced
+/// costs, such as `{2}{R} // {1}{R}`, counts.
+#[must_use]
+pub fn pips(mana_cost: &str) -> BTreeMap<Color, u32> {
+    let mut pips = BTreeMap::new();
+    let front = mana_cost.split(" // ").next().unwrap_or_default();
+    for symbol in front.split('}') {
+        for color in Color::ALL {
+            if symbol.contains(color.symbol()) {
+                *pips.entry(color).or_default() += 1;
+            }
+        }
+    }
+    pips
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ZoneAnalysis {
+    pub cards: u32,
+    /// Nonland cards by mana value, up to `7+`
+    pub curve: BTreeMap<u8, u32>,
+    pub types: BTreeMap<CardType, u32>,
+    pub pips: BTreeMap<Color, u32>,
+    /// Cards without card data
+    pub unknown: u32,
+}
+
+impl ZoneAnalysis {
+    fn add(&mut self, card_data: Option<&NetCardData>, amount: u8) {
+        let amount = u32::from(amount);
+        self.cards += amount;
+        let Some(card_data) = card_data else {
+            self.unknown += amount;
+            return;
+        };
+        let card_type = CardType::of(&card_data.type_line);
+        *self.types.entry(card_type).or_default() += amount;
+        if card_type != CardType::Land {
+            // Mana values are whole numbers, but for a few Un-cards
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let mana_value = (card_data.cmc as u8).min(CURVE_TOP);
+            *self.curve.entry(mana_value).or_default() += amount;
+        }
+        for (color, pips) in pips(&card_data.mana_cost) {
+            *self.pips.entry(color).or_default() += pips * amount;
+        }
+    }
+
+    #[must_use]
+    pub fn lands(&self) -> u32 {
+        self.types.get(&CardType::Land).copied().unwrap_or_default()
+    }
+
+    /// Nonland, noncreature cards
+    #[must_use]
+    pub fn spells(&self) -> u32 {
+        self.types
+            .iter()
+            .filter(|(card_type, _)| !matches!(card_type, CardType::Land | CardType::Creature))
+            .map(|(_, amount)| amount)
+            .sum()
+    }
+}
+
+impl fmt::Display for ZoneAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} cards, {} lands", self.cards, self.lands())?;
+        writeln!(f, "Curve")?;
+        let highest = self.curve.keys().max().copied().unwrap_or_default();
+        for mana_value in 0..=highest {
+            let amount = self.curve.get(&mana_value).copied().unwrap_or_default();
+            let label = if mana_value == CURVE_TOP {
+                format!("{CURVE_TOP}+")
+            } else {
+                mana_value.to_string()
+            };
+            writeln!(
+                f,
+                "  {label:>2} {:<20} {amount}",
+                "#".repeat(amount as usize)
+            )?;
+        }
+        let creatures = self
+            .types
+            .get(&CardType::Creature)
+            .copied()
+            .unwrap_or_default();
+        let spell_types = self
+            .types
+            .iter()
+            .filter(|(card_type, _)| !matches!(card_type, CardType::Land | CardType::Creature))
+            .map(|(card_type, amount)| format!("{amount} {card_type}"))
+            .join(", ");
+        write!(f, "Types\t {creatures} creatures, {} spells", self.spells())?;
+        if !spell_types.is_empty() {
+            write!(f, " ({spell_types})")?;
+        }
+        let pips = self
+            .pips
+            .iter()
+            .map(|(color, amount)| format!("{} {amount}", color.symbol()))
+            .join(", ");
+        write!(f, "\nPips\t {pips}")?;
+        if self.unknown > 0 {
+            write!(f, "\n{} cards have no card data", self.unknown)?;
+        }
+        Ok(())
+    }
+}
+
+/// Statistics of the main deck, and of the cards outside of it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeckAnalysis {
+    pub main: ZoneAnalysis,
+    /// Sideboard, wishboard and companion
+    pub side: ZoneAnalysis,
+}
+
+impl DeckAnalysis {
+    #[must_use]
+    pub fn new(deck: &Deck, card_data: &HashMap<String, NetCardData>) -> Self {
+        let mut analysis = Self::default();
+        let companion_listed = deck
+            .cards_by_zone()
+            .any(|(zone, name, _)| zone == Zone::Side && Some(name.as_str()) == deck.companion());
+        for (zone, name, amount) in deck.cards_by_zone() {
+            let zone_analysis = match zone {
+                Zone::Main => &mut analysis.main,
+                // Arena lists the companion in the sideboard too
+                Zone::Companion if companion_listed => continue,
+                Zone::Companion | Zone::Side | Zone::Wish => &mut analysis.side,
+            };
+            zone_analysis.add(card_data.get(name), amount);
+        }
+        analysis
+    }
+}
+
+impl fmt::Display for DeckAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Main deck: {}", self.main)?;
+        if self.side.cards > 0 {
+            write!(f, "\n\nSideboard: {}", self.side)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rarity;
+
+    fn card(name: &str, mana_cost: &str, cmc: f32, type_line: &str) -> (String, NetCardData) {
+        let data = NetCardData {
+            name: name.to_owned(),
+            rarity: Rarity::Common,
+            set: String::new(),
+            mana_cost: mana_cost.to_owned(),
+            cmc,
+            colors: Vec::new(),
+            color_identity: Vec::new(),
+            type_line: type_line.to_owned(),
+            oracle_text: String::new(),
+            arena_id: None,
+        };
+        (name.to_owned(), data)
+    }
+
+    #[test]
+    fn types_and_pips() {
+        assert_eq!(
+            CardType::of("Artifact Creature — Golem"),
+            CardType::Creature
+        );
+        assert_eq!(CardType::of("Sorcery // Land"), CardType::Sorcery);
+        assert_eq!(CardType::of("Basic Land — Mountain"), CardType::Land);
+        assert_eq!(
+            pips("{1}{W/U}{R}{R/P}"),
+            BTreeMap::from([(Color::White, 1), (Color::Blue, 1), (Color::Red, 2)])
+        );
+        assert_eq!(pips("{2}{R} // {1}{R}"), BTreeMap::from([(Color::Red, 1)]));
+        assert_eq!(
+            pips("{3}{G} // {1}{U}"),
+            BTreeMap::from([(Color::Green, 1)])
+        );
+    }
+
+    #[test]
+    fn analysis() {
+        let card_data = HashMap::from([
+            card("Mountain", "", 0.0, "Basic Land — Mountain"),
+            card("Shock", "{R}", 1.0, "Instant"),
+            card(
+                "Bonecrusher Giant // Stomp",
+                "{2}{R}",
+                3.0,
+                "Creature — Giant // Instant — Adventure",
+            ),
+            card(
+                "Emrakul, the Aeons Torn",
+                "{15}",
+                15.0,
+                "Legendary Creature — Eldrazi",
+            ),
+        ]);
+        let deck: Deck = "Deck\n20 Mountain\n4 Shock\n4 Bonecrusher Giant // Stomp\n1 Emrakul, the Aeons Torn\n\nSideboard\n2 Abrade\n"
+            .parse()
+            .unwrap();
+        let analysis = DeckAnalysis::new(&deck, &card_data);
+        assert_eq!(analysis.main.lands(), 20);
+        assert_eq!(analysis.main.spells(), 4);
+        assert_eq!(
+            analysis.main.curve,
+            BTreeMap::from([(1, 4), (3, 4), (7, 1)])
+        );
+        assert_eq!(analysis.main.pips, BTreeMap::from([(Color::Red, 8)]));
+        assert_eq!(analysis.side.unknown, 2);
+    }
+}
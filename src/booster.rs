@@ -0,0 +1,637 @@
+use crate::{
+    config::Config, cost_model::CostModel, Inventory, Rarity, Roster, Sideboard,
+    WildcardCoefficients,
+};
+use anyhow::Result;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Number of cards of each rarity in a set.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SetSize {
+    pub common: u16,
+    pub uncommon: u16,
+    pub rare: u16,
+    pub mythic: u16,
+}
+
+impl SetSize {
+    #[must_use]
+    pub fn select(&self, rarity: &Rarity) -> u16 {
+        match rarity {
+            Rarity::Common => self.common,
+            Rarity::Uncommon => self.uncommon,
+            Rarity::Rare => self.rare,
+            Rarity::Mythic => self.mythic,
+            Rarity::Land | Rarity::Unknown => 0,
+        }
+    }
+}
+
+/// Describes what a pack of a given set contains. The defaults follow the
+/// collation of regular Arena packs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Collation {
+    pub commons: u8,
+    pub uncommons: u8,
+    /// Chance that the rare slot is upgraded to a mythic
+    pub mythic_rate: f32,
+    /// Chance that one of the common slots is replaced by a common wildcard
+    pub common_wildcard_rate: f32,
+    /// Chance that one of the uncommon slots is replaced by an uncommon wildcard
+    pub uncommon_wildcard_rate: f32,
+    /// Every `rare_track` packs, the rare slot is replaced by a rare wildcard
+    pub rare_track: u32,
+    /// Every `mythic_track` packs, the wildcard from the rare track is a mythic one
+    pub mythic_track: u32,
+    /// When unset, the size of the set is estimated from the cards we know of
+    pub set_size: Option<SetSize>,
+}
+
+impl Default for Collation {
+    fn default() -> Self {
+        Self {
+            commons: 5,
+            uncommons: 2,
+            mythic_rate: 1.0 / 7.4,
+            common_wildcard_rate: 1.0 / 3.0,
+            uncommon_wildcard_rate: 1.0 / 5.0,
+            rare_track: 6,
+            mythic_track: 30,
+            set_size: None,
+        }
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn track_rate(interval: u32) -> f32 {
+    if interval == 0 {
+        0.0
+    } else {
+        1.0 / interval as f32
+    }
+}
+
+impl Collation {
+    fn rare_wildcard_rate(&self) -> f32 {
+        (track_rate(self.rare_track) - track_rate(self.mythic_track)).max(0.0)
+    }
+
+    fn mythic_wildcard_rate(&self) -> f32 {
+        track_rate(self.mythic_track)
+    }
+
+    fn card_in_rare_slot_rate(&self) -> f32 {
+        1.0 - self.rare_wildcard_rate() - self.mythic_wildcard_rate()
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PackConfig {
+    pub default: Collation,
+    /// Per-set overrides, keyed by set code
+    pub sets: HashMap<String, Collation>,
+}
+
+impl PackConfig {
+    #[must_use]
+    pub fn collation(&self, set: &str) -> &Collation {
+        self.sets.get(set).unwrap_or(&self.default)
+    }
+}
+
+/// How many points of vault progress duplicates are worth, and what opening
+/// the vault rewards. Progress is expressed in percent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Vault {
+    pub common_duplicate: f32,
+    pub uncommon_duplicate: f32,
+    pub reward_uncommons: u8,
+    pub reward_rares: u8,
+    pub reward_mythics: u8,
+}
+
+impl Default for Vault {
+    fn default() -> Self {
+        Self {
+            common_duplicate: 0.1,
+            uncommon_duplicate: 0.3,
+            reward_uncommons: 3,
+            reward_rares: 2,
+            reward_mythics: 1,
+        }
+    }
+}
+
+impl Vault {
+    fn duplicate_progress(&self, rarity: Rarity) -> f32 {
+        match rarity {
+            Rarity::Common => self.common_duplicate,
+            Rarity::Uncommon => self.uncommon_duplicate,
+            _ => 0.0,
+        }
+    }
+
+    #[must_use]
+    pub fn reward_value(&self, coeffs: &WildcardCoefficients) -> f32 {
+        f32::from(self.reward_mythics).mul_add(
+            coeffs.mythic,
+            f32::from(self.reward_uncommons)
+                .mul_add(coeffs.uncommon, f32::from(self.reward_rares) * coeffs.rare),
+        )
+    }
+}
+
+/// The shape of a draft: how many packs are opened, how many picks are made
+/// from each of them, and how many packs are won on average.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Draft {
+    pub packs: u8,
+    pub picks_per_pack: u8,
+    pub reward_packs: f32,
+}
+
+impl Default for Draft {
+    fn default() -> Self {
+        Self {
+            packs: 3,
+            picks_per_pack: 14,
+            reward_packs: 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Need {
+    missing: u8,
+    owned: u8,
+}
+
+enum Opened {
+    Needed,
+    Duplicate,
+    Other,
+}
+
+/// The cards of one rarity that a pack of a set can contain. Cards we need
+/// come first, followed by the others, which may be playsets we already own.
+#[derive(Debug, Default, Clone)]
+struct Slot {
+    size: usize,
+    complete: usize,
+    needs: Vec<Need>,
+}
+
+impl Slot {
+    fn available(&self, protected: bool) -> usize {
+        if protected {
+            self.size.saturating_sub(self.complete)
+        } else {
+            self.size
+        }
+    }
+
+    /// Chance that a card opened in this slot is one of the cards we need.
+    /// With duplicate protection, cards we own a playset of are never opened.
+    #[allow(clippy::cast_precision_loss)]
+    fn hit_rate(&self, protected: bool) -> f32 {
+        let available = self.available(protected);
+        if available == 0 {
+            return 0.0;
+        }
+        let needed = self.needs.iter().filter(|need| need.missing > 0).count();
+        needed as f32 / available as f32
+    }
+
+    /// Chance that a card opened in an unprotected slot is a 5th copy
+    #[allow(clippy::cast_precision_loss)]
+    fn duplicate_rate(&self) -> f32 {
+        if self.size == 0 {
+            return 0.0;
+        }
+        self.complete as f32 / self.size as f32
+    }
+
+    fn draw(&mut self, rng: &mut impl Rng, protected: bool) -> Opened {
+        let available = self.available(protected);
+        if available == 0 {
+            return Opened::Other;
+        }
+        let i = rng.gen_range(0..available);
+        let Some(need) = self.needs.get_mut(i) else {
+            let complete_needs = self.needs.iter().filter(|need| need.owned >= 4).count();
+            let complete_others = self.complete.saturating_sub(complete_needs);
+            let others = self.size - self.needs.len();
+            return if !protected && i - self.needs.len() < complete_others.min(others) {
+                Opened::Duplicate
+            } else {
+                Opened::Other
+            };
+        };
+        if need.owned >= 4 {
+            return Opened::Duplicate;
+        }
+        let opened = if need.missing > 0 {
+            Opened::Needed
+        } else {
+            Opened::Other
+        };
+        need.missing = need.missing.saturating_sub(1);
+        need.owned += 1;
+        if need.owned >= 4 {
+            self.complete += 1;
+            if protected {
+                self.needs.swap_remove(i);
+            }
+        }
+        opened
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct SetPool {
+    common: Slot,
+    uncommon: Slot,
+    rare: Slot,
+    mythic: Slot,
+}
+
+impl SetPool {
+    fn select_mut(&mut self, rarity: Rarity) -> Option<&mut Slot> {
+        match rarity {
+            Rarity::Common => Some(&mut self.common),
+            Rarity::Uncommon => Some(&mut self.uncommon),
+            Rarity::Rare => Some(&mut self.rare),
+            Rarity::Mythic => Some(&mut self.mythic),
+            Rarity::Land | Rarity::Unknown => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Simulation {
+    pub mean: f32,
+    pub std_dev: f32,
+}
+
+/// Estimates how much opening a pack of each set is worth to us, in terms of
+/// the wildcards we would otherwise have to spend on the roster.
+pub struct PackModel<'c> {
+    config: &'c Config,
+    coeffs: [f32; 4],
+    vault_point: f32,
+    vault_progress: f32,
+    pools: BTreeMap<String, SetPool>,
+}
+
+impl<'c> PackModel<'c> {
+    #[allow(clippy::missing_panics_doc)]
+    pub fn new(
+        config: &'c Config,
+        roster: &Roster,
+        inventory: &Inventory,
+        ignore_sideboard: bool,
+    ) -> Result<Self> {
+        // The copies needed to play every deck of the roster at once
+        let mut needed = HashMap::new();
+        for (card_name, amount) in
+            roster.cards(Sideboard::new(ignore_sideboard, &config.wish_cards))
+        {
+            let entry = needed.entry(card_name).or_insert(0);
+            *entry = amount.max(*entry);
+        }
+
+        let mut pools: BTreeMap<String, SetPool> = BTreeMap::new();
+        for (card_name, amount) in needed {
+            let owned = inventory.card_amount(card_name)?;
+            let missing = amount.saturating_sub(owned);
+            let mut prints = inventory.prints(card_name)?.clone();
+            prints.sort_unstable_by(|(_, r1, s1), (_, r2, s2)| (s1, r1).cmp(&(s2, r2)));
+            prints.dedup_by(|(_, r1, s1), (_, r2, s2)| (s1, r1) == (s2, r2));
+            for (_, rarity, set) in prints {
+                let protected = matches!(rarity, Rarity::Rare | Rarity::Mythic);
+                let pool = pools.entry(set).or_default();
+                match pool.select_mut(rarity) {
+                    Some(slot) if !(protected && owned >= 4) => {
+                        slot.needs.push(Need { missing, owned });
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let completion = inventory.collection().completion();
+        for (set, pool) in &mut pools {
+            let set_size = config.packs.collation(set).set_size;
+            for rarity in [
+                Rarity::Common,
+                Rarity::Uncommon,
+                Rarity::Rare,
+                Rarity::Mythic,
+            ] {
+                let slot = pool.select_mut(rarity).unwrap();
+                let known = completion
+                    .get(&(set.as_str(), rarity))
+                    .copied()
+                    .unwrap_or_default();
+                let configured = set_size.map_or(0, |size| usize::from(size.select(&rarity)));
+                slot.complete = known.playsets;
+                slot.size = known.known.max(configured).max(slot.needs.len());
+            }
+        }
+
+        let coeffs = inventory.wildcard_coeffs();
+        Ok(Self {
+            config,
+            coeffs: [coeffs.common, coeffs.uncommon, coeffs.rare, coeffs.mythic],
+            vault_point: config.vault.reward_value(coeffs) / 100.0,
+            vault_progress: inventory.vault_progress(),
+            pools,
+        })
+    }
+
+    /// Sets in which at least one card of the roster has been printed
+    pub fn sets(&self) -> impl Iterator<Item = &String> {
+        self.pools.keys()
+    }
+
+    /// Expected value of a card opened in an unprotected slot, counting the
+    /// vault progress granted by duplicates
+    fn card_value(&self, slot: &Slot, rarity: Rarity, coeff: f32) -> f32 {
+        let vault = self.config.vault.duplicate_progress(rarity) * self.vault_point;
+        slot.hit_rate(false)
+            .mul_add(coeff, slot.duplicate_rate() * vault)
+    }
+
+    /// Expected wildcard value of opening a single pack of `set`
+    #[must_use]
+    pub fn expected_value(&self, set: &str) -> f32 {
+        let Some(pool) = self.pools.get(set) else {
+            return 0.0;
+        };
+        let c = self.config.packs.collation(set);
+        let [common, uncommon, rare, mythic] = self.coeffs;
+
+        let commons = (f32::from(c.commons) - c.common_wildcard_rate).mul_add(
+            self.card_value(&pool.common, Rarity::Common, common),
+            c.common_wildcard_rate * common,
+        );
+        let uncommons = (f32::from(c.uncommons) - c.uncommon_wildcard_rate).mul_add(
+            self.card_value(&pool.uncommon, Rarity::Uncommon, uncommon),
+            c.uncommon_wildcard_rate * uncommon,
+        );
+        let card_in_rare_slot = (1.0 - c.mythic_rate).mul_add(
+            rare * pool.rare.hit_rate(true),
+            c.mythic_rate * mythic * pool.mythic.hit_rate(true),
+        );
+        let rare_slot = c.card_in_rare_slot_rate().mul_add(
+            card_in_rare_slot,
+            c.rare_wildcard_rate()
+                .mul_add(rare, c.mythic_wildcard_rate() * mythic),
+        );
+        commons + uncommons + rare_slot
+    }
+
+    /// Expected wildcard value of drafting `set`. We assume the first pick of
+    /// each pack is its rare if we need it, and that the other picks are
+    /// random commons and uncommons. Draft packs have neither wildcards nor
+    /// duplicate protection.
+    #[must_use]
+    pub fn draft_value(&self, set: &str) -> f32 {
+        let Some(pool) = self.pools.get(set) else {
+            return 0.0;
+        };
+        let c = self.config.packs.collation(set);
+        let d = &self.config.draft;
+        let [common, uncommon, rare, mythic] = self.coeffs;
+
+        let first_pick = (1.0 - c.mythic_rate).mul_add(
+            rare * pool.rare.hit_rate(false),
+            c.mythic_rate * mythic * pool.mythic.hit_rate(false),
+        );
+        let commons = f32::from(c.commons);
+        let uncommons = f32::from(c.uncommons);
+        let other_pick = commons.mul_add(
+            self.card_value(&pool.common, Rarity::Common, common),
+            uncommons * self.card_value(&pool.uncommon, Rarity::Uncommon, uncommon),
+        ) / (commons + uncommons).max(1.0);
+        let other_picks = f32::from(d.picks_per_pack.saturating_sub(1));
+        f32::from(d.packs).mul_add(
+            other_picks.mul_add(other_pick, first_pick),
+            d.reward_packs * self.expected_value(set),
+        )
+    }
+
+    /// Opens `packs` packs of `set`, `trials` times, and reports the average
+    /// value per pack along with its standard deviation across trials. Vault
+    /// progress starts from the current one, and the vault is opened whenever
+    /// it reaches 100%.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn simulate(
+        &self,
+        set: &str,
+        packs: usize,
+        trials: usize,
+        rng: &mut impl Rng,
+    ) -> Simulation {
+        let Some(pool) = self.pools.get(set) else {
+            return Simulation {
+                mean: 0.0,
+                std_dev: 0.0,
+            };
+        };
+        let c = self.config.packs.collation(set);
+        let vault = &self.config.vault;
+        let vault_reward = self.vault_point * 100.0;
+        let [common, uncommon, rare, mythic] = self.coeffs;
+        let results: Vec<f32> = (0..trials)
+            .map(|_| {
+                let mut pool = pool.clone();
+                let mut vault_progress = self.vault_progress;
+                let mut value = 0.0;
+                let mut open = |slot: &mut Slot, rng: &mut _, rarity, coeff| match slot
+                    .draw(rng, matches!(rarity, Rarity::Rare | Rarity::Mythic))
+                {
+                    Opened::Needed => value += coeff,
+                    Opened::Duplicate => {
+                        vault_progress += vault.duplicate_progress(rarity);
+                        if vault_progress >= 100.0 {
+                            vault_progress -= 100.0;
+                            value += vault_reward;
+                        }
+                    }
+                    Opened::Other => {}
+                };
+                let mut wildcards = 0.0;
+                for i in 1..=packs {
+                    let mut commons = c.commons;
+                    if commons > 0
+                        && rng.gen_bool(f64::from(c.common_wildcard_rate.clamp(0.0, 1.0)))
+                    {
+                        wildcards += common;
+                        commons -= 1;
+                    }
+                    for _ in 0..commons {
+                        open(&mut pool.common, rng, Rarity::Common, common);
+                    }
+                    let mut uncommons = c.uncommons;
+                    if uncommons > 0
+                        && rng.gen_bool(f64::from(c.uncommon_wildcard_rate.clamp(0.0, 1.0)))
+                    {
+                        wildcards += uncommon;
+                        uncommons -= 1;
+                    }
+                    for _ in 0..uncommons {
+                        open(&mut pool.uncommon, rng, Rarity::Uncommon, uncommon);
+                    }
+                    let on_track = |interval: u32| interval > 0 && i % interval as usize == 0;
+                    if on_track(c.mythic_track) {
+                        wildcards += mythic;
+                    } else if on_track(c.rare_track) {
+                        wildcards += rare;
+                    } else if rng.gen_bool(f64::from(c.mythic_rate.clamp(0.0, 1.0))) {
+                        open(&mut pool.mythic, rng, Rarity::Mythic, mythic);
+                    } else {
+                        open(&mut pool.rare, rng, Rarity::Rare, rare);
+                    }
+                }
+                (value + wildcards) / packs.max(1) as f32
+            })
+            .collect();
+
+        let n = results.len().max(1) as f32;
+        let mean = results.iter().sum::<f32>() / n;
+        let variance = results.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / n;
+        Simulation {
+            mean,
+            std_dev: variance.sqrt(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Wildcards;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn model(config: &Config, vault_progress: f32, pool: SetPool) -> PackModel<'_> {
+        PackModel {
+            config,
+            coeffs: [1.0, 2.0, 3.0, 4.0],
+            vault_point: 0.5,
+            vault_progress,
+            pools: BTreeMap::from([("set".to_owned(), pool)]),
+        }
+    }
+
+    #[test]
+    fn duplicate_protection_skips_complete_playsets() {
+        let slot = Slot {
+            size: 10,
+            complete: 6,
+            needs: vec![
+                Need {
+                    missing: 2,
+                    owned: 0,
+                },
+                Need {
+                    missing: 0,
+                    owned: 2,
+                },
+            ],
+        };
+        assert!((slot.hit_rate(false) - 0.1).abs() < f32::EPSILON);
+        assert!((slot.hit_rate(true) - 0.25).abs() < f32::EPSILON);
+        assert!((slot.duplicate_rate() - 0.6).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn draws_follow_hit_rate() {
+        let slot = Slot {
+            size: 4,
+            complete: 0,
+            needs: vec![Need {
+                missing: 4,
+                owned: 0,
+            }],
+        };
+        let mut rng = StdRng::seed_from_u64(7);
+        let hits = (0..4000)
+            .filter(|_| matches!(slot.clone().draw(&mut rng, true), Opened::Needed))
+            .count();
+        assert!((900..1100).contains(&hits));
+    }
+
+    #[test]
+    fn complete_playsets_open_duplicates_unless_protected() {
+        let mut slot = Slot {
+            size: 2,
+            complete: 2,
+            needs: Vec::new(),
+        };
+        let mut rng = StdRng::seed_from_u64(7);
+        assert!((slot.duplicate_rate() - 1.0).abs() < f32::EPSILON);
+        assert!(matches!(slot.draw(&mut rng, false), Opened::Duplicate));
+        assert!(matches!(slot.draw(&mut rng, true), Opened::Other));
+    }
+
+    #[test]
+    fn duplicates_fill_the_vault() {
+        let config = Config::default();
+        let pool = SetPool {
+            common: Slot {
+                size: 1,
+                complete: 1,
+                needs: Vec::new(),
+            },
+            ..SetPool::default()
+        };
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut model = model(&config, 99.95, pool);
+        model.coeffs = [0.0; 4];
+        let simulation = model.simulate("set", 1, 1, &mut rng);
+        assert!((simulation.mean - 50.0).abs() < f32::EPSILON);
+        model.vault_progress = 0.0;
+        let simulation = model.simulate("set", 1, 1, &mut rng);
+        assert!(simulation.mean.abs() < f32::EPSILON);
+
+        let wildcards: Wildcards =
+            serde_json::from_str(r#"{"common": 1, "uncommon": 2, "rare": 3, "mythic": 4}"#)
+                .unwrap();
+        assert!(wildcards.vault_progress.abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn drafts_pick_needed_rares_first() {
+        let config = Config::default();
+        let pool = SetPool {
+            rare: Slot {
+                size: 10,
+                complete: 0,
+                needs: vec![
+                    Need {
+                        missing: 1,
+                        owned: 3,
+                    };
+                    10
+                ],
+            },
+            ..SetPool::default()
+        };
+        let model = model(&config, 0.0, pool);
+        let c = Collation::default();
+        // Every rare is needed, no other card is
+        let first_pick = (1.0 - c.mythic_rate) * 3.0;
+        let expected = 3.0f32.mul_add(first_pick, 2.0 * model.expected_value("set"));
+        assert!((model.draft_value("set") - expected).abs() < 1e-5);
+        assert!(model.draft_value("set") > model.expected_value("set"));
+        assert!(model.draft_value("unknown").abs() < f32::EPSILON);
+    }
+}
@@ -0,0 +1,247 @@
+use crate::{
+    analysis::CardType, cost_model::CostModel, mtga_id_translator::NetCardData, Color, Deck,
+    Rarity, Sideboard,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+};
+
+/// Substitutes differ from the card they replace by at most this much mana
+const MAX_MANA_VALUE_GAP: f32 = 1.0;
+
+/// Copies of a card a budget variant plays instead of another
+#[derive(Hash, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Swap {
+    pub from: String,
+    pub to: String,
+    pub amount: u8,
+}
+
+impl fmt::Display for Swap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} -> {}", self.amount, self.from, self.to)
+    }
+}
+
+/// Swaps that bring the rares and mythics a deck is missing down to
+/// `max_rares`, and the missing copies left when there are not enough owned
+/// substitutes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BudgetPlan {
+    pub swaps: Vec<Swap>,
+    pub missing_rares: u32,
+}
+
+/// Finds owned substitutes for the missing rares and mythics of a deck, those
+/// missing the most copies first. Substitutes come from `table` when it lists
+/// owned cards, and else from `pool`: owned cards of the same type, within the
+/// color identity of the deck, closest in mana value and colors.
+#[allow(clippy::implicit_hasher)]
+pub fn plan(
+    deck: &Deck,
+    model: &impl CostModel,
+    card_data: &HashMap<String, NetCardData>,
+    pool: &[&NetCardData],
+    table: &BTreeMap<String, Vec<String>>,
+    max_rares: u32,
+) -> Result<BudgetPlan> {
+    let mut missing = model
+        .missing(deck, false)?
+        .into_iter()
+        .filter(|(name, _, rarity, _)| {
+            matches!(rarity, Rarity::Rare | Rarity::Mythic)
+                && deck.companion() != Some(name.as_str())
+        })
+        .map(|(name, amount, _, _)| (name, amount))
+        .collect::<Vec<_>>();
+    missing.sort_by(|(n1, a1), (n2, a2)| a2.cmp(a1).then(n1.cmp(n2)));
+    let mut missing_rares: u32 = missing.iter().map(|(_, amount)| u32::from(*amount)).sum();
+
+    let deck_identity: BTreeSet<Color> = deck
+        .card_names()
+        .filter_map(|name| card_data.get(name))
+        .flat_map(|data| data.color_identity.iter().copied())
+        .collect();
+    // Owned copies that the deck does not play yet
+    let spare_copies = |name: &str| -> u8 {
+        let owned = model.card_amount(name).unwrap_or_default();
+        let played = deck
+            .cards(Sideboard::Counted)
+            .find(|(played, _)| *played == name)
+            .map_or(0, |(_, amount)| amount);
+        owned.saturating_sub(played)
+    };
+    let mut swapped_in: HashMap<&str, u8> = HashMap::new();
+
+    let mut swaps = Vec::new();
+    for (name, amount) in missing {
+        if missing_rares <= max_rares {
+            break;
+        }
+        let from_table = table
+            .get(name.as_str())
+            .into_iter()
+            .flatten()
+            .map(String::as_str);
+        let similar = card_data
+            .get(name)
+            .map(|original| similar_cards(original, &deck_identity, pool))
+            .unwrap_or_default();
+        let mut left = amount;
+        for substitute in from_table.chain(similar) {
+            if left == 0 {
+                break;
+            }
+            let used = swapped_in.entry(substitute).or_default();
+            let copies = left.min(spare_copies(substitute).saturating_sub(*used));
+            if substitute == name || copies == 0 {
+                continue;
+            }
+            *used += copies;
+            left -= copies;
+            missing_rares -= u32::from(copies);
+            swaps.push(Swap {
+                from: name.clone(),
+                to: substitute.to_owned(),
+                amount: copies,
+            });
+        }
+    }
+    Ok(BudgetPlan {
+        swaps,
+        missing_rares,
+    })
+}
+
+/// Cards of the pool that can stand in for `original`, best first
+fn similar_cards<'p>(
+    original: &NetCardData,
+    deck_identity: &BTreeSet<Color>,
+    pool: &[&'p NetCardData],
+) -> Vec<&'p str> {
+    let card_type = CardType::of(&original.type_line);
+    let color_gap = |card: &NetCardData| {
+        card.color_identity
+            .iter()
+            .filter(|color| !original.color_identity.contains(color))
+            .count()
+            + original
+                .color_identity
+                .iter()
+                .filter(|color| !card.color_identity.contains(color))
+                .count()
+    };
+    let mut similar = pool
+        .iter()
+        .filter(|card| {
+            card.name != original.name
+                && CardType::of(&card.type_line) == card_type
+                && (card.cmc - original.cmc).abs() <= MAX_MANA_VALUE_GAP
+                && card
+                    .color_identity
+                    .iter()
+                    .all(|color| deck_identity.contains(color))
+        })
+        .collect::<Vec<_>>();
+    similar.sort_by(|c1, c2| {
+        let gap = |card: &NetCardData| (card.cmc - original.cmc).abs();
+        gap(c1)
+            .total_cmp(&gap(c2))
+            .then(color_gap(c1).cmp(&color_gap(c2)))
+            .then(c1.name.cmp(&c2.name))
+    });
+    similar.into_iter().map(|card| card.name.as_str()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost_model::FakeCollection;
+
+    fn card(name: &str, cmc: f32, identity: &[Color], type_line: &str) -> NetCardData {
+        NetCardData {
+            name: name.to_owned(),
+            rarity: Rarity::Common,
+            set: String::new(),
+            mana_cost: String::new(),
+            cmc,
+            colors: identity.to_vec(),
+            color_identity: identity.to_vec(),
+            type_line: type_line.to_owned(),
+            oracle_text: String::new(),
+            arena_id: None,
+        }
+    }
+
+    #[test]
+    fn substitutes() -> Result<()> {
+        let collection = FakeCollection::new(&[
+            ("Mountain", 0, Rarity::Land, "set"),
+            ("Shock", 4, Rarity::Common, "set"),
+            ("Bonecrusher Giant", 0, Rarity::Rare, "set"),
+            ("Fable of the Mirror-Breaker", 1, Rarity::Rare, "set"),
+            ("Phoenix Chick", 4, Rarity::Uncommon, "set"),
+            ("Kessig Flamebreather", 4, Rarity::Common, "set"),
+            ("Charging Monstrosaur", 4, Rarity::Uncommon, "set"),
+            ("Glorybringer", 0, Rarity::Rare, "set"),
+        ])
+        .with_costs([1.0; 5]);
+        let red = [Color::Red];
+        let card_data: HashMap<_, _> = [
+            card("Shock", 1.0, &red, "Instant"),
+            card("Bonecrusher Giant", 3.0, &red, "Creature — Giant"),
+            card("Glorybringer", 5.0, &red, "Creature — Dragon"),
+        ]
+        .into_iter()
+        .map(|data| (data.name.clone(), data))
+        .collect();
+        let owned = [
+            card("Phoenix Chick", 1.0, &red, "Creature — Phoenix"),
+            card("Kessig Flamebreather", 2.0, &red, "Creature — Human"),
+            card("Charging Monstrosaur", 5.0, &red, "Creature — Dinosaur"),
+            card("Llanowar Elves", 1.0, &[Color::Green], "Creature — Elf"),
+        ];
+        let pool = owned.iter().collect::<Vec<_>>();
+        let deck: Deck =
+            "Deck\n20 Mountain\n4 Shock\n4 Bonecrusher Giant\n4 Glorybringer\n".parse()?;
+        let table = BTreeMap::from([(
+            "Glorybringer".to_owned(),
+            vec!["Fable of the Mirror-Breaker".to_owned()],
+        )]);
+
+        let budget = plan(&deck, &collection, &card_data, &pool, &table, 0)?;
+        assert_eq!(budget.missing_rares, 0);
+        let swaps = budget
+            .swaps
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            swaps,
+            [
+                "4 Bonecrusher Giant -> Kessig Flamebreather",
+                "1 Glorybringer -> Fable of the Mirror-Breaker",
+                "3 Glorybringer -> Charging Monstrosaur",
+            ]
+        );
+
+        let variant = deck.variant("Budget", budget.swaps);
+        assert_eq!(variant.variant_of(), Some(deck.name.as_str()));
+        assert_eq!(
+            variant
+                .cards(Sideboard::Counted)
+                .map(|(_, amount)| amount)
+                .sum::<u8>(),
+            32
+        );
+        assert!(!variant.contains(&"Glorybringer", Sideboard::Counted));
+
+        let budget = plan(&deck, &collection, &card_data, &pool, &table, 4)?;
+        assert_eq!(budget.swaps.len(), 1);
+        assert_eq!(budget.missing_rares, 4);
+        Ok(())
+    }
+}
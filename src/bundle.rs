@@ -0,0 +1,138 @@
+use crate::Deck;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, path::Path, str::FromStr};
+
+const BUNDLE_VERSION: u32 = 1;
+
+/// Decks exported from one roster, to be imported in another
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RosterBundle {
+    version: u32,
+    pub decks: Vec<Deck>,
+}
+
+impl RosterBundle {
+    #[must_use]
+    pub fn new(decks: Vec<Deck>) -> Self {
+        Self {
+            version: BUNDLE_VERSION,
+            decks,
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bundle: Self = serde_json::from_str(&fs::read_to_string(path)?)
+            .with_context(|| format!("Failed to parse roster bundle {}", path.display()))?;
+        if bundle.version > BUNDLE_VERSION {
+            bail!(
+                "The bundle {} was made by a newer version of detr (format {}, expected at most {BUNDLE_VERSION})",
+                path.display(),
+                bundle.version
+            );
+        }
+        Ok(bundle)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// What to do with an imported deck whose name is taken by a different list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnConflict {
+    /// Imports the deck under a new name
+    Rename,
+    /// Keeps the local deck
+    Skip,
+    /// Overwrites the local deck
+    Replace,
+}
+
+impl FromStr for OnConflict {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rename" => Ok(Self::Rename),
+            "skip" => Ok(Self::Skip),
+            "replace" => Ok(Self::Replace),
+            _ => bail!("Expected one of rename, skip and replace, found `{s}`"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeOutcome {
+    Added(String),
+    /// The roster already has the same list, under this name
+    Identical {
+        imported: String,
+        local: String,
+    },
+    Renamed {
+        imported: String,
+        renamed: String,
+    },
+    Skipped(String),
+    Replaced(String),
+}
+
+impl fmt::Display for MergeOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Added(name) => write!(f, "Added `{name}`"),
+            Self::Identical { imported, local } if imported == local => {
+                write!(f, "Already up to date `{local}`")
+            }
+            Self::Identical { imported, local } => {
+                write!(f, "Already present `{imported}`, as `{local}`")
+            }
+            Self::Renamed { imported, renamed } => {
+                write!(f, "Renamed `{imported}` to `{renamed}`, its name was taken")
+            }
+            Self::Skipped(name) => write!(f, "Skipped `{name}`, its name was taken"),
+            Self::Replaced(name) => write!(f, "Replaced `{name}`"),
+        }
+    }
+}
+
+/// What importing a bundle changed in the roster
+#[derive(Debug, Clone, Default)]
+pub struct MergeSummary {
+    pub outcomes: Vec<MergeOutcome>,
+}
+
+impl MergeSummary {
+    /// Number of decks that were added or replaced
+    #[must_use]
+    pub fn changed(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|outcome| {
+                matches!(
+                    outcome,
+                    MergeOutcome::Added(_)
+                        | MergeOutcome::Renamed { .. }
+                        | MergeOutcome::Replaced(_)
+                )
+            })
+            .count()
+    }
+}
+
+impl fmt::Display for MergeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for outcome in &self.outcomes {
+            writeln!(f, "{outcome}")?;
+        }
+        write!(
+            f,
+            "{} of {} decks changed the roster",
+            self.changed(),
+            self.outcomes.len()
+        )
+    }
+}
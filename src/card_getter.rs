@@ -1,5 +1,7 @@
-use crate::mtga_id_translator::{MtgaIdTranslator, NetCardData};
-use crate::{Collection, Rarity};
+use crate::collection::simplified_name;
+use crate::mtga_id_translator::{MtgaIdTranslator, NetCardData, ScryfallCard};
+use crate::player_log::{ArenaDeck, PlayerLog};
+use crate::{CardVersion, Collection, Deck};
 use anyhow::{anyhow, Context, Result};
 use indicatif::ProgressIterator;
 use reqwest::Url;
@@ -27,10 +29,9 @@ enum ScryfallGames {
 
 #[derive(Deserialize)]
 struct ScryfallCardData {
-    name: String,
     games: Vec<ScryfallGames>,
-    rarity: Rarity,
-    set: String,
+    #[serde(flatten)]
+    card: ScryfallCard,
 }
 
 #[derive(Deserialize)]
@@ -55,7 +56,69 @@ impl CardGetter {
             )?
             .json::<DaemonReply>()
             .context("Unable to parse json from card daemon. Make sure the game is idling in the main menu.")?.cards;
-        let collection = cards
+        Ok(Self::translate(translator, cards))
+    }
+
+    /// Reads the collection from the game log instead of the daemon, so that
+    /// neither needs to be running
+    pub fn owned_cards_from_log(
+        translator: &mut MtgaIdTranslator,
+        log: &PlayerLog,
+    ) -> Result<Collection> {
+        let cards = log.cards.as_ref().context(
+            "The game log holds no card collection. Make sure detailed logs are enabled in the game options, then restart the game.",
+        )?;
+        let cards = cards
+            .iter()
+            .map(|(&id, &owned)| NameAmount { id, owned })
+            .collect();
+        Ok(Self::translate(translator, cards))
+    }
+
+    /// Turns a deck of the Arena client into a deck of the roster, linked to it
+    pub fn arena_deck(translator: &mut MtgaIdTranslator, deck: &ArenaDeck) -> Result<Deck> {
+        let mut name_of = |id: u32| -> Result<String> {
+            let card_data = translator
+                .translate(id)?
+                .ok_or(anyhow!("Card with id {id} does not exist on scryfall"))?;
+            Ok(card_data.name)
+        };
+        let mut cards = |cards: &[(u32, u8)]| {
+            cards
+                .iter()
+                .map(|&(id, amount)| Ok((amount, name_of(id)?)))
+                .collect::<Result<Vec<_>>>()
+        };
+        let main = cards(&deck.main)?;
+        let side = cards(&deck.side)?;
+        let companion = deck.companion.map(&mut name_of).transpose()?;
+        Ok(Deck::from_parts(
+            &deck.name,
+            companion,
+            main,
+            side,
+            Some(deck.id.clone()),
+        ))
+    }
+
+    /// Names and amounts of cards given once per copy, as in game logs
+    pub fn card_list(translator: &mut MtgaIdTranslator, ids: &[u32]) -> Result<Vec<(u8, String)>> {
+        let mut amounts: Vec<(u8, String)> = Vec::new();
+        for &id in ids {
+            let name = translator
+                .translate(id)?
+                .ok_or(anyhow!("Card with id {id} does not exist on scryfall"))?
+                .name;
+            match amounts.iter_mut().find(|(_, listed)| *listed == name) {
+                Some((amount, _)) => *amount = amount.saturating_add(1),
+                None => amounts.push((1, name)),
+            }
+        }
+        Ok(amounts)
+    }
+
+    fn translate(translator: &mut MtgaIdTranslator, cards: Vec<NameAmount>) -> Collection {
+        cards
             .into_iter()
             .progress()
             .flat_map(|NameAmount { id, owned }| {
@@ -64,13 +127,17 @@ impl CardGetter {
                     .ok_or(anyhow!("Card with id {id} does not exist on scryfall"))?;
                 Ok::<_, anyhow::Error>((card_data.name, owned, card_data.rarity, card_data.set))
             })
-            .collect::<Collection>();
-        Ok(collection)
+            .collect::<Collection>()
     }
 
     pub fn fetch_card(name: impl AsRef<str>) -> Result<Vec<NetCardData>> {
-        let name = crate::collection::simplified_name(&name);
-        let to_parse = format!("https://api.scryfall.com/cards/search?q={name}&unique=prints",);
+        let name = simplified_name(&name);
+        // The `A-` prefix would otherwise be read as a negated search term
+        let query = match CardVersion::of(name) {
+            CardVersion::Original => name.to_owned(),
+            CardVersion::Rebalanced => format!("!\"{name}\""),
+        };
+        let to_parse = format!("https://api.scryfall.com/cards/search?q={query}&unique=prints");
         let url =
             Url::parse(&to_parse).with_context(|| anyhow!("Failed to parse url {to_parse}"))?;
         let prints: Vec<ScryfallCardData> = reqwest::blocking::get(url)
@@ -81,18 +148,33 @@ impl CardGetter {
         let relevant = prints
             .into_iter()
             .filter(|print| print.games.contains(&ScryfallGames::Arena))
-            .map(
-                |ScryfallCardData {
-                     name,
-                     rarity,
-                     set,
-                    ..
-                 }| NetCardData { name, rarity, set },
-            )
+            .map(|print| print.card.into())
             .collect();
         Ok(relevant)
     }
 
+    /// Everything scryfall knows about a card, from the translator cache when
+    /// it has it, so that it works offline
+    pub fn card_data(translator: &mut MtgaIdTranslator, name: &str) -> Result<NetCardData> {
+        if let Some(card_data) = translator.find(name).filter(|card| card.is_complete()) {
+            return Ok(card_data.clone());
+        }
+        let prints = Self::fetch_card(name)?;
+        for print in &prints {
+            translator.remember(print.clone());
+        }
+        Self::print_named(prints, name).ok_or(anyhow!("Unable to find {name} on Arena"))
+    }
+
+    /// The first print of the card called `name`, skipping the other cards a
+    /// fuzzy search returns
+    fn print_named(prints: Vec<NetCardData>, name: &str) -> Option<NetCardData> {
+        let wanted = simplified_name(&name);
+        prints
+            .into_iter()
+            .find(|print| simplified_name(&print.name).eq_ignore_ascii_case(wanted))
+    }
+
     #[cfg(test)]
     pub fn status() -> Result<serde_json::Value> {
         let url = format!("{}/status", Self::address());
@@ -105,6 +187,44 @@ impl CardGetter {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::Rarity;
+
+    fn print(name: &str, set: &str) -> NetCardData {
+        NetCardData {
+            name: name.to_owned(),
+            rarity: Rarity::Common,
+            set: set.to_owned(),
+            mana_cost: String::new(),
+            cmc: 0.0,
+            colors: Vec::new(),
+            color_identity: Vec::new(),
+            type_line: String::new(),
+            oracle_text: String::new(),
+            arena_id: None,
+        }
+    }
+
+    #[test]
+    fn near_misses_are_skipped() {
+        let prints = vec![
+            print("Aftershock", "ktk"),
+            print("Shock", "m21"),
+            print("Shock Troops", "dom"),
+        ];
+        assert_eq!(
+            CardGetter::print_named(prints.clone(), "shock").map(|print| print.set),
+            Some("m21".to_owned())
+        );
+        assert_eq!(
+            CardGetter::print_named(
+                vec![print("Bonecrusher Giant // Stomp", "eld")],
+                "Bonecrusher Giant"
+            )
+            .map(|print| print.set),
+            Some("eld".to_owned())
+        );
+        assert_eq!(CardGetter::print_named(prints, "Shocker"), None);
+    }
 
     #[test]
     fn get_status() {
@@ -1,8 +1,10 @@
 use crate::{
-    card_getter::CardGetter, mtga_id_translator::NetCardData, CardData, Deck, Rarity, Roster,
+    card_getter::CardGetter, mtga_id_translator::NetCardData, name_index::NameIndex, CardData,
+    CardVersion, Deck, Rarity, Roster, Sideboard,
 };
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use indicatif::ProgressBar;
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
@@ -10,13 +12,22 @@ use std::{
     path::Path,
 };
 
+/// Keeps the front face of split and double-faced cards. Rebalanced cards keep
+/// their `A-` prefix, since they are distinct from their original version.
 pub(crate) fn simplified_name(name: &impl AsRef<str>) -> &str {
     name.as_ref()
-        .trim_start_matches("A-")
         .split_once(" // ")
         .map_or(name.as_ref(), |split| split.0)
 }
 
+/// How many cards of a set and rarity we know of, and how many of them we own
+/// a playset of.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Completion {
+    pub known: usize,
+    pub playsets: usize,
+}
+
 #[derive(Default, Clone, Debug, Serialize, Deserialize)]
 pub struct Collection {
     content: HashMap<String, Vec<(u8, Rarity, String)>>,
@@ -100,6 +111,55 @@ impl Collection {
         self.content.keys()
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Vec<(u8, Rarity, String)>)> {
+        self.content.iter()
+    }
+
+    /// Collections made before rebalanced cards were kept apart stored them
+    /// under the name of their original version as well. This drops those rows
+    /// so that they can be fetched again.
+    pub fn separate_rebalanced(&mut self) {
+        let rebalanced: Vec<(String, Vec<String>)> = self
+            .content
+            .iter()
+            .filter_map(|(name, group)| {
+                let original = CardVersion::original_name(name);
+                (original != name.as_str()).then(|| {
+                    let sets = group.iter().map(|(_, _, set)| set.clone()).collect();
+                    (original.to_owned(), sets)
+                })
+            })
+            .collect();
+        for (original, sets) in rebalanced {
+            if let Some(group) = self.content.get_mut(&original) {
+                group.retain(|(_, _, set)| !sets.contains(set));
+                if group.is_empty() {
+                    self.content.remove(&original);
+                }
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn completion(&self) -> HashMap<(&str, Rarity), Completion> {
+        let mut completion: HashMap<_, Completion> = HashMap::new();
+        for (name, group) in &self.content {
+            // Split cards are stored under both their full and simplified names
+            if simplified_name(name) != name {
+                continue;
+            }
+            let owned: u8 = group.iter().map(|(amount, _, _)| amount).sum();
+            for (rarity, set) in group.iter().map(|(_, rarity, set)| (*rarity, set)).unique() {
+                let entry = completion.entry((set.as_str(), rarity)).or_default();
+                entry.known += 1;
+                if owned >= 4 {
+                    entry.playsets += 1;
+                }
+            }
+        }
+        completion
+    }
+
     pub fn merge(&mut self, other: Self) {
         for (other_name, other_group) in other.content {
             for other_row in other_group {
@@ -109,8 +169,8 @@ impl Collection {
     }
 
     pub fn ensure_known(&mut self, roster: &Roster) {
-        let pb = ProgressBar::new(roster.cards(false).count() as u64);
-        for (name, _) in pb.wrap_iter(roster.cards(false)) {
+        let pb = ProgressBar::new(roster.cards(Sideboard::Counted).count() as u64);
+        for (name, _) in pb.wrap_iter(roster.cards(Sideboard::Counted)) {
             let name = simplified_name(&name);
             if !self.content.contains_key(name) {
                 if let Err(err) = self.fetch_unknown(name) {
@@ -123,61 +183,123 @@ impl Collection {
 
     pub fn get(&self, name: impl AsRef<str>) -> Result<&Vec<(u8, Rarity, String)>> {
         let name = simplified_name(&name);
-        self.content.get(name).ok_or(anyhow!(
-            "Unknown card found: {name}. Make sure to run `detr update-collection` before.",
-        ))
+        self.content.get(name).ok_or_else(|| {
+            let index = NameIndex::new(self.names());
+            let suggestions = index.suggestions(name, 3);
+            let hint = if suggestions.is_empty() {
+                String::new()
+            } else {
+                format!(" Did you mean {}?", suggestions.join(", "))
+            };
+            anyhow!(
+                "Unknown card found: {name}.{hint} Make sure to run `detr update-collection` before.",
+            )
+        })
     }
 
-    fn fetch_unknown(&mut self, name: impl AsRef<str>) -> Result<()> {
-        let card_data = CardGetter::fetch_card(&name)?;
-        for NetCardData { name, rarity, set } in card_data {
-            self.insert(CardData {
-                amount: 0,
-                name,
-                rarity,
-                set,
-            });
+    fn knows(&self, name: &str) -> bool {
+        self.content.contains_key(simplified_name(&name))
+    }
+
+    /// The name a card is stored under, ignoring case, punctuation and the
+    /// back face of split and double-faced cards
+    pub fn canonical_name(&self, name: impl AsRef<str>) -> Result<String> {
+        let name = simplified_name(&name);
+        if self.content.contains_key(name) {
+            return Ok(name.to_owned());
+        }
+        match NameIndex::new(self.names()).exact(name) {
+            Some(known) => Ok(known.to_owned()),
+            None => self.get(name).map(|_| name.to_owned()),
         }
-        Ok(())
     }
 
-    #[allow(clippy::missing_panics_doc)]
-    pub fn missing<'a, 'b: 'a>(
-        &'a self,
-        deck: &'b Deck,
-        ignore_sideboard: bool,
-    ) -> Result<Vec<(&'b String, u8, Rarity, &'a String)>> {
-        let mut missing = Vec::new();
-        for (name, deck_amount) in deck.cards(ignore_sideboard) {
-            let card_group = self.get(name)?;
-            let owned_amount = card_group.iter().map(|(amount, _, _)| amount).sum();
-            let (set_name, lowest_rarity) = card_group
-                .iter()
-                .map(|(_, rarity, set_name)| (set_name, rarity))
-                .min_by_key(|(_, rarity)| *rarity)
-                .unwrap(); // We can unwrap here because self.get returns early if the card_group is empty
-            let missing_amount = deck_amount.saturating_sub(owned_amount);
-            if missing_amount > 0 {
-                missing.push((name, missing_amount, *lowest_rarity, set_name));
+    /// Makes sure every card of `deck` is known. Names that differ only in
+    /// case or punctuation are fixed, and cards missing from the collection are
+    /// fetched. Misspelled names are replaced by their closest match when
+    /// `auto_fix` is set. Fails with every name that could not be resolved.
+    pub fn resolve_names(
+        &mut self,
+        deck: &mut Deck,
+        auto_fix: bool,
+    ) -> Result<Vec<(String, String)>> {
+        let index = NameIndex::new(self.names());
+        let unknown = deck
+            .card_names()
+            .filter(|name| !self.knows(name))
+            .unique()
+            .cloned()
+            .collect_vec();
+        let mut fixes = Vec::new();
+        let mut misspelled = Vec::new();
+        for name in unknown {
+            if let Some(known) = index.exact(&name) {
+                fixes.push((name, known.to_owned()));
+                continue;
+            }
+            // The card may simply be missing from the collection
+            match self.fetch_unknown(&name) {
+                Ok(known) if known == name => {}
+                Ok(known) => fixes.push((name, known)),
+                Err(_) => misspelled.push(name),
+            }
+        }
+
+        // Built again, so that cards fetched above can be suggested
+        let index = NameIndex::new(self.names());
+        let mut unresolved = Vec::new();
+        for name in misspelled {
+            let suggestions = index.suggestions(&name, 3);
+            match suggestions.first() {
+                Some(best) if auto_fix => fixes.push((name, (*best).to_owned())),
+                Some(_) => unresolved.push(format!(
+                    "`{name}`, did you mean {}?",
+                    suggestions.iter().map(|s| format!("`{s}`")).join(", ")
+                )),
+                None => unresolved.push(format!("`{name}`")),
             }
         }
-        Ok(missing)
+        if !unresolved.is_empty() {
+            bail!(
+                "Unknown cards found: {}. Fix the decklist, or use `--auto-fix` to pick the closest matches.",
+                unresolved.join("; ")
+            );
+        }
+        for (from, to) in &fixes {
+            deck.rename_card(from, to);
+        }
+        Ok(fixes)
     }
 
-    pub fn count_missing_of_rarity(
-        &self,
-        deck: &Deck,
-        ignore_sideboard: bool,
-        rarity: Rarity,
-    ) -> Result<usize> {
-        let count = self
-            .missing(deck, ignore_sideboard)?
+    /// Adds the Arena prints of a card to the collection, with no owned copy.
+    /// Only prints of that very card are kept, not the other results of the
+    /// search. Returns the name of the card, as scryfall spells it.
+    fn fetch_unknown(&mut self, name: impl AsRef<str>) -> Result<String> {
+        let wanted = simplified_name(&name);
+        let prints = CardGetter::fetch_card(wanted)?
             .into_iter()
-            .filter_map(|(_, amount, this_rarity, _)| {
-                (this_rarity == rarity).then_some(amount as usize)
-            })
-            .sum();
-        Ok(count)
+            .filter(|print| simplified_name(&print.name).eq_ignore_ascii_case(wanted))
+            .collect_vec();
+        let Some(first) = prints.first() else {
+            bail!("No card named {wanted} on Arena");
+        };
+        let known = if name.as_ref().contains(" // ") {
+            first.name.clone()
+        } else {
+            simplified_name(&first.name).to_owned()
+        };
+        for NetCardData {
+            name, rarity, set, ..
+        } in prints
+        {
+            self.insert(CardData {
+                amount: 0,
+                name,
+                rarity,
+                set,
+            });
+        }
+        Ok(known)
     }
 }
 
@@ -193,3 +315,104 @@ impl FromIterator<(String, u8, Rarity, String)> for Collection {
         Self { content }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completion_counts_playsets_once_per_card() {
+        let collection: Collection = [
+            ("Shock", 4, Rarity::Common, "m21"),
+            ("Opt", 2, Rarity::Common, "m21"),
+            ("Bonecrusher Giant // Stomp", 4, Rarity::Rare, "eld"),
+            ("Bonecrusher Giant", 4, Rarity::Rare, "eld"),
+        ]
+        .into_iter()
+        .map(|(name, amount, rarity, set)| (name.to_owned(), amount, rarity, set.to_owned()))
+        .collect();
+        let completion = collection.completion();
+        assert_eq!(
+            completion[&("m21", Rarity::Common)],
+            Completion {
+                known: 2,
+                playsets: 1
+            }
+        );
+        assert_eq!(
+            completion[&("eld", Rarity::Rare)],
+            Completion {
+                known: 1,
+                playsets: 1
+            }
+        );
+    }
+
+    #[test]
+    fn rebalanced_cards_stay_apart() {
+        assert_eq!(simplified_name(&"A-Shock"), "A-Shock");
+        assert_eq!(simplified_name(&"A-Fable // Reflection"), "A-Fable");
+        let mut collection: Collection = [
+            ("Lier, Disciple of the Drowned", 4, Rarity::Mythic, "mid"),
+            ("Lier, Disciple of the Drowned", 1, Rarity::Mythic, "ymid"),
+            ("A-Lier, Disciple of the Drowned", 1, Rarity::Mythic, "ymid"),
+            ("Shock", 4, Rarity::Common, "m21"),
+        ]
+        .into_iter()
+        .map(|(name, amount, rarity, set)| (name.to_owned(), amount, rarity, set.to_owned()))
+        .collect();
+        collection.separate_rebalanced();
+        let sets = |name| {
+            collection
+                .get(name)
+                .unwrap()
+                .iter()
+                .map(|(_, _, set)| set.as_str())
+                .collect_vec()
+        };
+        assert_eq!(sets("Lier, Disciple of the Drowned"), ["mid"]);
+        assert_eq!(sets("A-Lier, Disciple of the Drowned"), ["ymid"]);
+        assert_eq!(sets("Shock"), ["m21"]);
+    }
+
+    #[test]
+    fn misspelled_names_are_suggested() -> Result<()> {
+        let mut collection: Collection = [("Lightning Strike", 4, Rarity::Common, "m19")]
+            .into_iter()
+            .map(|(name, amount, rarity, set)| (name.to_owned(), amount, rarity, set.to_owned()))
+            .collect();
+        let mut deck: Deck = "Deck\n4 lightning strike\n4 Lightnig Strike\n".parse()?;
+        let err = collection
+            .resolve_names(&mut deck.clone(), false)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("`Lightnig Strike`, did you mean `Lightning Strike`?"));
+
+        let fixes = collection.resolve_names(&mut deck, true)?;
+        assert_eq!(fixes.len(), 2);
+        assert!(deck.card_names().all(|name| name == "Lightning Strike"));
+        Ok(())
+    }
+
+    #[test]
+    fn canonical_names() -> Result<()> {
+        let collection: Collection = [
+            ("Alchemist's Gambit", Rarity::Rare),
+            ("Ojer Taq, Deepest Foundation", Rarity::Mythic),
+        ]
+        .into_iter()
+        .map(|(name, rarity)| (name.to_owned(), 0, rarity, "set".to_owned()))
+        .collect();
+        assert_eq!(
+            collection.canonical_name("alchemists gambit")?,
+            "Alchemist's Gambit"
+        );
+        assert_eq!(
+            collection.canonical_name("Ojer Taq, Deepest Foundation // Temple of Civilization")?,
+            "Ojer Taq, Deepest Foundation"
+        );
+        assert!(collection.canonical_name("Alchemist's Gamble").is_err());
+        Ok(())
+    }
+}
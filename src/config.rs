@@ -0,0 +1,50 @@
+use crate::{
+    booster::{Draft, PackConfig, Vault},
+    cost_model::DeckCostStrategy,
+    sets::RotationConfig,
+    WishCards,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File},
+    path::Path,
+};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub packs: PackConfig,
+    pub vault: Vault,
+    pub draft: Draft,
+    pub deck_cost: DeckCostStrategy,
+    /// Cards `suggest` should never recommend crafting
+    pub never_craft: BTreeSet<String>,
+    /// Cards that make decks need their wishboard, or the start of their
+    /// sideboard, even when sideboards are ignored
+    pub wish_cards: WishCards,
+    /// Cards `budget` should play instead of a missing card, when they are
+    /// owned, best first
+    pub substitutes: BTreeMap<String, Vec<String>>,
+    pub rotation: RotationConfig,
+}
+
+impl Config {
+    /// Reads the configuration file, falling back to the defaults for every
+    /// missing field, or for the whole config if the file does not exist.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let config = serde_json::from_reader(File::open(path)?)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))?;
+        Ok(config)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,398 @@
+use crate::{Deck, Rarity, Sideboard, WishCards};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Parameters of the closeness formula: decks missing less than `rares` rares
+/// and `mythics` mythics worth of wildcards are all scored `floor`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Closeness {
+    pub rares: f32,
+    pub mythics: f32,
+    pub floor: f32,
+}
+
+impl Default for Closeness {
+    fn default() -> Self {
+        Self {
+            rares: 4.0,
+            mythics: 1.0,
+            floor: 1.0,
+        }
+    }
+}
+
+/// How the cost of a whole deck is scored, for `list` and `suggest`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "kebab-case")]
+pub enum DeckCostStrategy {
+    /// Wildcard cost of the missing cards
+    Raw,
+    /// Share of the wildcard cost of the deck that is still missing, in percent
+    Completion,
+    /// Wildcard cost of the missing cards, minus a closeness bound
+    Closeness(Closeness),
+}
+
+impl Default for DeckCostStrategy {
+    fn default() -> Self {
+        Self::Closeness(Closeness::default())
+    }
+}
+
+impl fmt::Display for DeckCostStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Raw => write!(f, "raw wildcard cost"),
+            Self::Completion => write!(f, "missing share of the wildcard cost, in percent"),
+            Self::Closeness(Closeness {
+                rares,
+                mythics,
+                floor,
+            }) => write!(
+                f,
+                "wildcard cost minus {rares} rares and {mythics} mythics, at least {floor:.2}"
+            ),
+        }
+    }
+}
+
+/// Decides which print of a card we would craft, and what it costs us. Every
+/// command goes through this trait, so that they all agree on the rarity and
+/// cost of a missing card.
+///
+/// Implementors only describe what they know of the cards and how much each
+/// rarity is worth; the provided methods are the default way of pricing cards
+/// and decks from there.
+pub trait CostModel {
+    /// Every print of a card that we know of, with the copies we own of it
+    fn prints(&self, card_name: &str) -> Result<&Vec<(u8, Rarity, String)>>;
+
+    /// What crafting a card of this rarity costs us
+    fn rarity_cost(&self, rarity: Rarity) -> f32;
+
+    /// Rarities, from the one we would rather craft to the one we would rather not
+    fn rarity_order(&self) -> [Rarity; 5];
+
+    /// Cards that fetch sideboard cards, which count even when the sideboard
+    /// is ignored
+    fn wish_cards(&self) -> &WishCards;
+
+    fn sideboard(&self, ignore_sideboard: bool) -> Sideboard<'_> {
+        Sideboard::new(ignore_sideboard, self.wish_cards())
+    }
+
+    fn cheapest_rarity(&self, card_name: &str) -> Result<Rarity> {
+        let prints = self.prints(card_name)?;
+        self.rarity_order()
+            .into_iter()
+            .find(|rarity| prints.iter().any(|(_, r, _)| r == rarity))
+            .or_else(|| prints.first().map(|(_, rarity, _)| *rarity))
+            .ok_or_else(|| anyhow!("No print of {card_name} is known"))
+    }
+
+    fn cheapest_version(&self, card_name: &str) -> Result<&(u8, Rarity, String)> {
+        let cheapest_rarity = self.cheapest_rarity(card_name)?;
+        self.prints(card_name)?
+            .iter()
+            .find(|(_, rarity, _)| *rarity == cheapest_rarity)
+            .ok_or_else(|| anyhow!("No print of {card_name} is known"))
+    }
+
+    fn card_cost(&self, card_name: &str) -> Result<f32> {
+        Ok(self.rarity_cost(self.cheapest_rarity(card_name)?))
+    }
+
+    /// Copies owned across all prints, up to a playset
+    fn card_amount(&self, card_name: &str) -> Result<u8> {
+        let in_collection: u8 = self
+            .prints(card_name)?
+            .iter()
+            .map(|(amount, _, _)| *amount)
+            .sum();
+        Ok(in_collection.clamp(0, 4))
+    }
+
+    /// Copies we would have to craft to play `deck_amount` copies. Basic lands
+    /// are free, so they are never missing.
+    fn missing_amount(&self, card_name: &str, deck_amount: u8) -> Result<u8> {
+        if self.cheapest_rarity(card_name)? == Rarity::Land {
+            return Ok(0);
+        }
+        Ok(deck_amount.saturating_sub(self.card_amount(card_name)?))
+    }
+
+    /// Favours cards of which few copies are missing, so that they win ties
+    /// against cards missing more copies
+    fn tiebreaker_bonus(&self, missing: u8) -> f32 {
+        if missing == 0 {
+            0.0
+        } else {
+            4.0 / f32::from(missing)
+        }
+    }
+
+    /// This function computes the importance of a card, with regard to how many
+    /// copies a deck plays.
+    fn card_cost_considering_deck(&self, card_name: &str, in_deck_amount: u8) -> Result<f32> {
+        let missing = self.missing_amount(card_name, in_deck_amount)?;
+        if missing == 0 {
+            Ok(0.0)
+        } else {
+            let tiebreaker_bonus = self.tiebreaker_bonus(missing);
+            Ok((self.card_cost(card_name)?).mul_add(f32::from(missing), tiebreaker_bonus))
+        }
+    }
+
+    fn missing<'a, 'b: 'a>(
+        &'a self,
+        deck: &'b Deck,
+        ignore_sideboard: bool,
+    ) -> Result<Vec<(&'b String, u8, Rarity, &'a String)>> {
+        let mut missing = Vec::new();
+        for (name, deck_amount) in deck.cards(self.sideboard(ignore_sideboard)) {
+            let missing_amount = self.missing_amount(name, deck_amount)?;
+            if missing_amount > 0 {
+                let (_, rarity, set_name) = self.cheapest_version(name)?;
+                missing.push((name, missing_amount, *rarity, set_name));
+            }
+        }
+        Ok(missing)
+    }
+
+    fn deck_cost(
+        &self,
+        deck: &Deck,
+        ignore_sideboard: bool,
+        strategy: &DeckCostStrategy,
+    ) -> Result<f32> {
+        let mut missing_cost = 0.0;
+        let mut total_cost = 0.0;
+        for (card_name, amount) in deck.cards(self.sideboard(ignore_sideboard)) {
+            let card_cost = self.card_cost(card_name)?;
+            let missing = self.missing_amount(card_name, amount)?;
+            missing_cost += f32::from(missing) * card_cost;
+            total_cost += f32::from(amount) * card_cost;
+        }
+        if missing_cost.abs() < f32::EPSILON {
+            return Ok(0.0);
+        }
+        let cost = match strategy {
+            DeckCostStrategy::Raw => missing_cost,
+            DeckCostStrategy::Completion => 100.0 * missing_cost / total_cost,
+            DeckCostStrategy::Closeness(Closeness {
+                rares,
+                mythics,
+                floor,
+            }) => {
+                let closeness_bound = rares.mul_add(
+                    self.rarity_cost(Rarity::Rare),
+                    mythics * self.rarity_cost(Rarity::Mythic),
+                );
+                f32::max(missing_cost - closeness_bound, *floor)
+            }
+        };
+        Ok(cost)
+    }
+
+    fn count_missing_of_rarity(
+        &self,
+        deck: &Deck,
+        ignore_sideboard: bool,
+        rarity: Rarity,
+    ) -> Result<usize> {
+        let count = self
+            .missing(deck, ignore_sideboard)?
+            .into_iter()
+            .filter_map(|(_, amount, this_rarity, _)| {
+                (this_rarity == rarity).then_some(amount as usize)
+            })
+            .sum();
+        Ok(count)
+    }
+}
+
+/// A collection made of the given prints, for the tests of the modules that
+/// price cards
+#[cfg(test)]
+pub(crate) struct FakeCollection {
+    prints: std::collections::HashMap<String, Vec<(u8, Rarity, String)>>,
+    order: [Rarity; 5],
+    /// Cost of each rarity, by its position in `order`
+    costs: [f32; 5],
+    wish_cards: WishCards,
+}
+
+#[cfg(test)]
+impl FakeCollection {
+    /// Prints are `(name, amount, rarity, set)`. Rarities are crafted from
+    /// lands to mythics, and cost 1, 1, 2, 4 and 8.
+    pub(crate) fn new(cards: &[(&str, u8, Rarity, &str)]) -> Self {
+        let mut prints = std::collections::HashMap::<_, Vec<_>>::new();
+        for (name, amount, rarity, set) in cards {
+            prints.entry((*name).to_owned()).or_default().push((
+                *amount,
+                *rarity,
+                (*set).to_owned(),
+            ));
+        }
+        Self {
+            prints,
+            order: [
+                Rarity::Land,
+                Rarity::Common,
+                Rarity::Uncommon,
+                Rarity::Rare,
+                Rarity::Mythic,
+            ],
+            costs: [1.0, 1.0, 2.0, 4.0, 8.0],
+            wish_cards: WishCards::default(),
+        }
+    }
+
+    pub(crate) fn with_order(self, order: [Rarity; 5]) -> Self {
+        Self { order, ..self }
+    }
+
+    pub(crate) fn with_costs(self, costs: [f32; 5]) -> Self {
+        Self { costs, ..self }
+    }
+}
+
+#[cfg(test)]
+impl CostModel for FakeCollection {
+    fn prints(&self, card_name: &str) -> Result<&Vec<(u8, Rarity, String)>> {
+        self.prints
+            .get(card_name)
+            .ok_or_else(|| anyhow!("Unknown card"))
+    }
+
+    fn rarity_cost(&self, rarity: Rarity) -> f32 {
+        let position = self.order.iter().position(|r| *r == rarity).unwrap();
+        self.costs[position]
+    }
+
+    fn rarity_order(&self) -> [Rarity; 5] {
+        self.order
+    }
+
+    fn wish_cards(&self) -> &WishCards {
+        &self.wish_cards
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Prices rarities by their position in the order
+    const POSITIONS: [f32; 5] = [0.0, 1.0, 2.0, 3.0, 4.0];
+
+    #[test]
+    fn cheapest_rarity_follows_the_order() -> Result<()> {
+        let cards = [
+            ("Ojer Taq", 0, Rarity::Mythic, "set"),
+            ("Ojer Taq", 0, Rarity::Rare, "set"),
+            ("Shock", 0, Rarity::Uncommon, "set"),
+            ("Shock", 0, Rarity::Common, "set"),
+        ];
+        let collection = FakeCollection::new(&cards).with_costs(POSITIONS);
+        assert_eq!(collection.cheapest_rarity("Ojer Taq")?, Rarity::Rare);
+        assert_eq!(collection.cheapest_rarity("Shock")?, Rarity::Common);
+
+        // Spare uncommon wildcards can make uncommons cheaper than commons
+        let order = [
+            Rarity::Land,
+            Rarity::Uncommon,
+            Rarity::Common,
+            Rarity::Rare,
+            Rarity::Mythic,
+        ];
+        let collection = FakeCollection::new(&cards)
+            .with_order(order)
+            .with_costs(POSITIONS);
+        assert_eq!(collection.cheapest_rarity("Shock")?, Rarity::Uncommon);
+        assert!((collection.card_cost("Shock")? - 1.0).abs() < f32::EPSILON);
+        assert!(collection.cheapest_rarity("Opt").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn missing_cards() -> Result<()> {
+        let collection = FakeCollection::new(&[
+            ("Mountain", 0, Rarity::Land, "set"),
+            ("Shock", 1, Rarity::Common, "set"),
+            ("Shock", 1, Rarity::Uncommon, "set"),
+            ("Bonecrusher Giant", 1, Rarity::Rare, "set"),
+            ("Glorybringer", 0, Rarity::Rare, "set"),
+            ("Embercleave", 3, Rarity::Mythic, "set"),
+        ])
+        .with_costs(POSITIONS);
+        assert_eq!(collection.missing_amount("Mountain", 20)?, 0);
+        assert_eq!(collection.missing_amount("Shock", 4)?, 2);
+        assert_eq!(collection.missing_amount("Embercleave", 2)?, 0);
+
+        let deck: Deck = "Deck\n20 Mountain\n4 Shock\n4 Bonecrusher Giant\n2 Embercleave\n\n\
+            Sideboard\n2 Glorybringer\n1 Embercleave\n"
+            .parse()?;
+        assert_eq!(
+            collection.count_missing_of_rarity(&deck, false, Rarity::Rare)?,
+            5
+        );
+        assert_eq!(
+            collection.count_missing_of_rarity(&deck, true, Rarity::Rare)?,
+            3
+        );
+        assert_eq!(
+            collection.count_missing_of_rarity(&deck, false, Rarity::Common)?,
+            2
+        );
+        assert_eq!(
+            collection.count_missing_of_rarity(&deck, false, Rarity::Land)?,
+            0
+        );
+        assert_eq!(
+            collection.count_missing_of_rarity(&deck, false, Rarity::Mythic)?,
+            0
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn strategy_from_config() {
+        let strategy: DeckCostStrategy =
+            serde_json::from_str(r#"{"strategy": "closeness", "rares": 2}"#).unwrap();
+        let DeckCostStrategy::Closeness(closeness) = strategy else {
+            panic!("Expected the closeness strategy, found {strategy:?}");
+        };
+        assert!((closeness.rares - 2.0).abs() < f32::EPSILON);
+        assert!((closeness.floor - 1.0).abs() < f32::EPSILON);
+
+        let strategy: DeckCostStrategy =
+            serde_json::from_str(r#"{"strategy": "completion"}"#).unwrap();
+        assert!(matches!(strategy, DeckCostStrategy::Completion));
+    }
+
+    #[test]
+    fn fewer_missing_copies_win_ties() -> Result<()> {
+        let collection = FakeCollection::new(&[
+            ("Mountain", 0, Rarity::Land, "set"),
+            ("Shock", 3, Rarity::Common, "set"),
+            ("Fable of the Mirror-Breaker", 2, Rarity::Rare, "set"),
+        ])
+        .with_costs(POSITIONS);
+        assert!(collection.tiebreaker_bonus(0).abs() < f32::EPSILON);
+        assert!((collection.tiebreaker_bonus(1) - 4.0).abs() < f32::EPSILON);
+        assert!((collection.tiebreaker_bonus(4) - 1.0).abs() < f32::EPSILON);
+
+        // Rares cost 3 here: 2 missing copies are worth 6, plus 4 / 2
+        let fable = collection.card_cost_considering_deck("Fable of the Mirror-Breaker", 4)?;
+        assert!((fable - 8.0).abs() < f32::EPSILON);
+        let shock = collection.card_cost_considering_deck("Shock", 4)?;
+        assert!((shock - 5.0).abs() < f32::EPSILON);
+        assert!(collection.card_cost_considering_deck("Shock", 3)?.abs() < f32::EPSILON);
+        assert!(collection.card_cost_considering_deck("Mountain", 20)?.abs() < f32::EPSILON);
+        Ok(())
+    }
+}
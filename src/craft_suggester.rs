@@ -1,15 +1,16 @@
+use anyhow::{Context, Result};
 use bitvec::{bitvec, vec::BitVec};
 use itertools::Itertools;
 use std::collections::{BTreeSet, HashMap};
 
-use crate::{collection::Collection, Deck, Rarity, Roster};
+use crate::{cost_model::CostModel, Deck, Rarity, Roster, Sideboard};
 
 fn build_matrix(decks: &[&Deck], rows_index: &BTreeSet<(&String, u8)>) -> Vec<BitVec> {
     let mut columns: Vec<BitVec> = Vec::new();
     for deck in decks {
         let mut column = vec![false; rows_index.len()];
         for (i, (rare, _)) in rows_index.iter().enumerate() {
-            column[i] = deck.contains(*rare, false);
+            column[i] = deck.contains(*rare, Sideboard::Counted);
         }
         let column = BitVec::from_iter(column);
         columns.push(column);
@@ -27,7 +28,7 @@ pub struct CraftRecommender<'r, 'c> {
     rares_limit: usize,
     mythics_limit: usize,
     roster: &'r Roster,
-    collection: &'c Collection,
+    cost_model: &'c dyn CostModel,
     starting_sel: Vec<String>,
     ignore_sb: bool,
 }
@@ -40,7 +41,7 @@ impl<'r, 'c> CraftRecommender<'r, 'c> {
         ignore_sideboard: bool,
         starting_sel: Option<Vec<String>>,
         roster: &'r Roster,
-        collection: &'c Collection,
+        cost_model: &'c dyn CostModel,
     ) -> Self {
         CraftRecommender {
             rares_limit: rares,
@@ -48,14 +49,21 @@ impl<'r, 'c> CraftRecommender<'r, 'c> {
             ignore_sb: ignore_sideboard,
             starting_sel: starting_sel.unwrap_or_default(),
             roster,
-            collection,
+            cost_model,
         }
     }
 
-    fn build_rows_index(&self, decks: &[&'r Deck], target: Rarity) -> BTreeSet<(&String, u8)> {
+    fn build_rows_index(
+        &self,
+        decks: &[&'r Deck],
+        target: Rarity,
+    ) -> Result<BTreeSet<(&'r String, u8)>> {
         let mut rows_index = BTreeSet::new();
         for deck in decks {
-            let missing = self.collection.missing(deck, self.ignore_sb).unwrap();
+            let missing = self
+                .cost_model
+                .missing(deck, self.ignore_sb)
+                .with_context(|| format!("Failed to find missing cards of `{}`", deck.name))?;
             for (name, amount, rarity, _) in missing {
                 if rarity == target {
                     for n in 1..=amount {
@@ -64,45 +72,46 @@ impl<'r, 'c> CraftRecommender<'r, 'c> {
                 }
             }
         }
-        rows_index
+        Ok(rows_index)
     }
 
-    fn relevant_decks(&self) -> Vec<&Deck> {
-        self.roster
-            .decks()
-            .filter(|deck| {
-                let missing_rares = self
-                    .collection
-                    .count_missing_of_rarity(deck, self.ignore_sb, Rarity::Rare)
-                    .unwrap();
-                let missing_mythics = self
-                    .collection
-                    .count_missing_of_rarity(deck, self.ignore_sb, Rarity::Mythic)
-                    .unwrap();
-                0 < missing_rares + missing_mythics
-                    && missing_rares <= self.rares_limit
-                    && missing_mythics <= self.mythics_limit
-            })
-            .collect()
+    fn relevant_decks(&self) -> Result<Vec<&'r Deck>> {
+        let mut decks = Vec::new();
+        for deck in self.roster.decks() {
+            let count = |rarity| {
+                self.cost_model
+                    .count_missing_of_rarity(deck, self.ignore_sb, rarity)
+                    .with_context(|| format!("Failed to find missing cards of `{}`", deck.name))
+            };
+            let missing_rares = count(Rarity::Rare)?;
+            let missing_mythics = count(Rarity::Mythic)?;
+            if 0 < missing_rares + missing_mythics
+                && missing_rares <= self.rares_limit
+                && missing_mythics <= self.mythics_limit
+            {
+                decks.push(deck);
+            }
+        }
+        Ok(decks)
     }
 
     /// Returns recommended crafts in the order of importance, following our
     /// homemade cool algorithm.  Given a `wildcards horizon`, that is the number
     /// of wildcards the user is expected to obtain in a certain amount of time
     /// (usually 25 rares for 3 months of play), it maximizes the number of
-    /// different number of decks the user can play
-    #[must_use]
-    pub fn recommend(&self) -> Vec<Vec<&str>> {
+    /// different number of decks the user can play. Fails if a deck plays a
+    /// card we know nothing of.
+    pub fn recommend(&self) -> Result<Vec<Vec<&str>>> {
         // Get the decks for which we are missing at least a rare or mythic card
-        let decks: Vec<_> = self.relevant_decks();
+        let decks: Vec<_> = self.relevant_decks()?;
 
         // Build the memo table
         let mut mem = HashMap::new();
 
         // Build the matrix
         // Get the rows
-        let all_rares = self.build_rows_index(&decks, Rarity::Rare);
-        let all_mythics = self.build_rows_index(&decks, Rarity::Mythic);
+        let all_rares = self.build_rows_index(&decks, Rarity::Rare)?;
+        let all_mythics = self.build_rows_index(&decks, Rarity::Mythic)?;
 
         // Get the columns
         let columns_rares = build_matrix(&decks, &all_rares);
@@ -126,7 +135,7 @@ impl<'r, 'c> CraftRecommender<'r, 'c> {
 
         // Turn the codes back into deck names
         let decks_codes: Vec<_> = result.iter().map(|(decks, _)| decks).collect();
-        decks_codes
+        let recommendations = decks_codes
             .iter()
             .map(|decks_code| {
                 decks
@@ -135,7 +144,8 @@ impl<'r, 'c> CraftRecommender<'r, 'c> {
                     .filter_map(|(i, deck)| (decks_code[i]).then_some(deck.name.as_str()))
                     .collect()
             })
-            .collect()
+            .collect();
+        Ok(recommendations)
     }
 
     fn recommend_inner(
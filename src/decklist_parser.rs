@@ -0,0 +1,349 @@
+use crate::Deck;
+use anyhow::{bail, Result};
+use itertools::Itertools;
+use regex::Regex;
+use std::fmt;
+
+/// Where a decklist seems to come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// Exported from Arena, which is what `Deck::from_str` reads
+    Arena,
+    /// Sideboard cards prefixed with `SB:`
+    Mtgo,
+    /// Bullets, `4x Card` or `Card x4` notation, category headers
+    Forum,
+    /// A page saved from a deck site
+    Html,
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Arena => write!(f, "Arena export"),
+            Self::Mtgo => write!(f, "MTGO export"),
+            Self::Forum => write!(f, "forum post"),
+            Self::Html => write!(f, "web page"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParsedDecklist {
+    pub deck: Deck,
+    pub dialect: Dialect,
+    /// Lines that were neither a card nor a header
+    pub ignored: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Zone {
+    Companion,
+    Main,
+    Side,
+    Wish,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Line {
+    Blank,
+    /// `Deck`, `Sideboard (15)`, `Companion`, `Wishboard`...
+    Section(Zone),
+    /// `Creatures (12)`, `Lands:`...
+    Category,
+    Card {
+        amount: u8,
+        name: String,
+        sideboard: bool,
+    },
+    Other(String),
+}
+
+struct Patterns {
+    section: Regex,
+    category: Regex,
+    card: Regex,
+    card_amount_last: Regex,
+    bullet: Regex,
+    sideboard_prefix: Regex,
+}
+
+impl Patterns {
+    fn new() -> Self {
+        let new = |re: &str| Regex::new(re).expect("Pattern should be valid");
+        Self {
+            section: new(
+                r"(?i)^(companion|deck|main|main ?deck|side ?board|sb|wish ?board)\s*(\(\d+\))?\s*:?$",
+            ),
+            category: new(
+                r"(?i)^([a-z][a-z ,/&-]*\s*(\(\d+\)|:)|(creatures?|lands?|instants?|sorcery|sorceries|artifacts?|enchantments?|planeswalkers?|battles?|spells|other spells))$",
+            ),
+            card: new(r"^(\d+)\s*([xX])?\s+(\S.*)$"),
+            card_amount_last: new(r"^(\S.*?)\s+[xX]\s*(\d+)$"),
+            bullet: new(r"^[-*•·+]\s+"),
+            sideboard_prefix: new(r"(?i)^sb:\s*"),
+        }
+    }
+
+    fn classify(&self, line: &str) -> (Line, bool) {
+        let line = line.trim();
+        if line.is_empty() {
+            return (Line::Blank, false);
+        }
+        let without_bullet = self.bullet.replace(line, "");
+        let mut forum = without_bullet.len() != line.len();
+        let line = without_bullet.trim();
+        if let Some(captures) = self.section.captures(line) {
+            let zone = match captures[1].to_lowercase().as_str() {
+                "companion" => Zone::Companion,
+                "deck" | "main" | "maindeck" | "main deck" => Zone::Main,
+                "wishboard" | "wish board" => Zone::Wish,
+                _ => Zone::Side,
+            };
+            forum |= captures.get(2).is_some() || line.ends_with(':');
+            return (Line::Section(zone), forum);
+        }
+        let sideboard = self.sideboard_prefix.is_match(line);
+        let line = self.sideboard_prefix.replace(line, "");
+        let card = if let Some(captures) = self.card.captures(&line) {
+            forum |= captures.get(2).is_some();
+            Some((captures[1].to_owned(), captures[3].to_owned()))
+        } else if let Some(captures) = self.card_amount_last.captures(&line) {
+            forum = true;
+            Some((captures[2].to_owned(), captures[1].to_owned()))
+        } else {
+            None
+        };
+        match card {
+            Some((amount, name)) => match amount.parse() {
+                Ok(amount) => (
+                    Line::Card {
+                        amount,
+                        name: name.trim().to_owned(),
+                        sideboard,
+                    },
+                    forum,
+                ),
+                Err(_) => (Line::Other(line.into_owned()), forum),
+            },
+            None if self.category.is_match(&line) => (Line::Category, true),
+            None => (Line::Other(line.into_owned()), forum),
+        }
+    }
+}
+
+fn looks_like_html(text: &str) -> bool {
+    let lowercase = text.to_lowercase();
+    ["<html", "<body", "<div", "<table", "<li", "<br", "<span"]
+        .iter()
+        .any(|tag| lowercase.contains(tag))
+}
+
+/// Keeps the text of a page, one line per block or table row
+fn html_to_text(html: &str) -> String {
+    let invisible = Regex::new(r"(?is)<(script|style|head)\b.*?</(script|style|head)>")
+        .expect("Pattern should be valid");
+    let line_break = Regex::new(r"(?i)<br\s*/?>|</(p|div|li|tr|h\d|ul|ol|table|section)>")
+        .expect("Pattern should be valid");
+    let tag = Regex::new(r"<[^>]*>").expect("Pattern should be valid");
+    let numeric_entity = Regex::new(r"&#(x?)([0-9a-fA-F]+);").expect("Pattern should be valid");
+    let text = invisible.replace_all(html, "");
+    let text = line_break.replace_all(&text, "\n");
+    let text = tag.replace_all(&text, " ");
+    let text = numeric_entity.replace_all(&text, |captures: &regex::Captures| {
+        let radix = if captures[1].is_empty() { 10 } else { 16 };
+        u32::from_str_radix(&captures[2], radix)
+            .ok()
+            .and_then(char::from_u32)
+            .map(String::from)
+            .unwrap_or_default()
+    });
+    text.replace("&nbsp;", " ")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+        .lines()
+        .map(|line| line.split_whitespace().join(" "))
+        .join("\n")
+}
+
+/// Reads decklists in the formats they are usually shared in, by turning them
+/// into an Arena export first.
+///
+/// Without a sideboard header, a blank line after the main deck starts the
+/// sideboard, as in Arena exports. Lines that are neither cards nor headers,
+/// like the rest of a web page, are ignored and reported.
+pub fn parse_decklist(text: &str) -> Result<ParsedDecklist> {
+    let html = looks_like_html(text);
+    let text = if html {
+        html_to_text(text)
+    } else {
+        text.to_owned()
+    };
+    let patterns = Patterns::new();
+    let mut forum = false;
+    let lines = text
+        .lines()
+        .map(|line| {
+            let (line, is_forum) = patterns.classify(line);
+            forum |= is_forum;
+            line
+        })
+        .collect_vec();
+    let sideboard_announced = lines.iter().any(|line| {
+        matches!(
+            line,
+            Line::Section(Zone::Side | Zone::Wish)
+                | Line::Category
+                | Line::Card {
+                    sideboard: true,
+                    ..
+                }
+        )
+    });
+
+    let mut zone = Zone::Main;
+    let mut companion = None;
+    let mut main = Vec::new();
+    let mut side = Vec::new();
+    let mut wish = Vec::new();
+    let mut ignored = Vec::new();
+    for line in lines {
+        match line {
+            Line::Blank if !sideboard_announced && !main.is_empty() => zone = Zone::Side,
+            Line::Section(section) => zone = section,
+            Line::Category if zone == Zone::Companion => zone = Zone::Main,
+            Line::Blank | Line::Category => {}
+            Line::Card {
+                amount,
+                name,
+                sideboard,
+            } => match zone {
+                _ if sideboard => side.push(format!("{amount} {name}")),
+                Zone::Companion => companion = Some(name),
+                Zone::Main => main.push(format!("{amount} {name}")),
+                Zone::Side => side.push(format!("{amount} {name}")),
+                Zone::Wish => wish.push(format!("{amount} {name}")),
+            },
+            Line::Other(line) => ignored.push(line),
+        }
+    }
+    if main.is_empty() && side.is_empty() && wish.is_empty() {
+        bail!("Found no card in the decklist");
+    }
+    let mut arena_export = Vec::new();
+    if let Some(companion) = companion {
+        arena_export.push(format!("Companion\n1 {companion}"));
+    }
+    arena_export.push(format!("Deck\n{}", main.join("\n")));
+    if !side.is_empty() {
+        arena_export.push(format!("Sideboard\n{}", side.join("\n")));
+    }
+    if !wish.is_empty() {
+        arena_export.push(format!("Wishboard\n{}", wish.join("\n")));
+    }
+
+    let has_sideboard_prefix = text
+        .lines()
+        .any(|line| patterns.sideboard_prefix.is_match(line.trim()));
+    let dialect = if html {
+        Dialect::Html
+    } else if forum {
+        Dialect::Forum
+    } else if has_sideboard_prefix {
+        Dialect::Mtgo
+    } else {
+        Dialect::Arena
+    };
+    Ok(ParsedDecklist {
+        deck: arena_export.join("\n").parse()?,
+        dialect,
+        ignored,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arena(list: &str) -> Deck {
+        list.parse().unwrap()
+    }
+
+    #[test]
+    fn arena_exports_are_unchanged() {
+        let list = "Companion\n1 Lurrus of the Dream-Den\n\nDeck\n4 Shock (M21) 159\n20 Mountain\n\nSideboard\n2 Abrade\n";
+        let parsed = parse_decklist(list).unwrap();
+        assert_eq!(parsed.dialect, Dialect::Arena);
+        assert_eq!(parsed.deck, arena(list));
+        assert!(parsed.ignored.is_empty());
+    }
+
+    #[test]
+    fn blank_line_starts_the_sideboard() {
+        let parsed = parse_decklist("4 Shock\n20 Mountain\n\n2 Abrade\n").unwrap();
+        assert_eq!(parsed.dialect, Dialect::Arena);
+        assert_eq!(
+            parsed.deck,
+            arena("Deck\n4 Shock\n20 Mountain\nSideboard\n2 Abrade\n")
+        );
+    }
+
+    #[test]
+    fn forum_posts() {
+        let post = "Creatures (4)\n- 4x Bonecrusher Giant\n\nInstants (4)\n* Shock x4\n\nLands (20)\n• 20 Mountain\n\nSideboard (2)\n2x Abrade\n";
+        let parsed = parse_decklist(post).unwrap();
+        assert_eq!(parsed.dialect, Dialect::Forum);
+        assert_eq!(
+            parsed.deck,
+            arena("Deck\n4 Bonecrusher Giant\n4 Shock\n20 Mountain\nSideboard\n2 Abrade\n")
+        );
+    }
+
+    #[test]
+    fn sideboard_prefix() {
+        let parsed = parse_decklist("4 Shock\n20 Mountain\nSB: 2 Abrade\n").unwrap();
+        assert_eq!(parsed.dialect, Dialect::Mtgo);
+        assert_eq!(
+            parsed.deck,
+            arena("Deck\n4 Shock\n20 Mountain\nSideboard\n2 Abrade\n")
+        );
+    }
+
+    #[test]
+    fn saved_web_pages() {
+        let page = r#"<html><head><title>Mono Red</title><style>td { color: red }</style></head>
+            <body><h1>Mono Red by someone</h1><table>
+            <tr><th>Main Deck (28)</th></tr>
+            <tr><td>4</td><td><a href="/card/1">Bonecrusher Giant</a></td></tr>
+            <tr><td>4</td><td>Fable of the Mirror&#45;Breaker</td></tr>
+            <tr><td>20</td><td>Mountain</td></tr>
+            <tr><th>Sideboard (2)</th></tr>
+            <tr><td>2</td><td>Jaya&apos;s Greeting</td></tr>
+            </table></body></html>"#;
+        let parsed = parse_decklist(page).unwrap();
+        assert_eq!(parsed.dialect, Dialect::Html);
+        assert_eq!(
+            parsed.deck,
+            arena("Deck\n4 Bonecrusher Giant\n4 Fable of the Mirror-Breaker\n20 Mountain\nSideboard\n2 Jaya's Greeting\n")
+        );
+        assert_eq!(parsed.ignored, vec!["Mono Red by someone"]);
+    }
+
+    #[test]
+    fn wishboard_header() {
+        let parsed =
+            parse_decklist("Main deck:\n1 Wish\n20 Mountain\n\nWishboard:\n1 Shock\n").unwrap();
+        assert_eq!(
+            parsed.deck,
+            arena("Deck\n1 Wish\n20 Mountain\nWishboard\n1 Shock\n")
+        );
+    }
+
+    #[test]
+    fn no_cards() {
+        assert!(parse_decklist("Just some text\n").is_err());
+    }
+}
@@ -1,10 +1,15 @@
-use crate::collection::Collection;
+use crate::{
+    budget::Swap,
+    bundle::{MergeOutcome, MergeSummary, OnConflict},
+    collection::Collection,
+    cost_model::CostModel,
+};
 use anyhow::{anyhow, bail, Context, Result};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use std::{
     cmp::Ordering,
-    collections::HashMap,
+    collections::{BTreeSet, HashMap},
     fs::{self, File},
     io::Write,
     mem,
@@ -12,10 +17,58 @@ use std::{
     str::FromStr,
 };
 
+pub mod analysis;
+pub mod booster;
+pub mod budget;
+pub mod bundle;
 pub mod card_getter;
 pub mod collection;
+pub mod config;
+pub mod cost_model;
 pub mod craft_suggester;
+pub mod decklist_parser;
+pub mod manabase;
+pub mod match_history;
 pub mod mtga_id_translator;
+pub mod name_index;
+pub mod odds;
+pub mod player_log;
+pub mod server;
+pub mod sets;
+pub mod simulation;
+pub mod suggestions;
+pub mod tui;
+pub mod validation;
+
+#[derive(Hash, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Color {
+    #[serde(rename = "W")]
+    White,
+    #[serde(rename = "U")]
+    Blue,
+    #[serde(rename = "B")]
+    Black,
+    #[serde(rename = "R")]
+    Red,
+    #[serde(rename = "G")]
+    Green,
+}
+
+impl Color {
+    pub const ALL: [Self; 5] = [Self::White, Self::Blue, Self::Black, Self::Red, Self::Green];
+
+    /// The letter of the color in mana costs
+    #[must_use]
+    pub const fn symbol(self) -> char {
+        match self {
+            Self::White => 'W',
+            Self::Blue => 'U',
+            Self::Black => 'B',
+            Self::Red => 'R',
+            Self::Green => 'G',
+        }
+    }
+}
 
 #[derive(Hash, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -29,6 +82,47 @@ pub enum Rarity {
     Unknown,
 }
 
+impl FromStr for Rarity {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "c" | "common" => Ok(Self::Common),
+            "u" | "uncommon" => Ok(Self::Uncommon),
+            "r" | "rare" => Ok(Self::Rare),
+            "m" | "mythic" => Ok(Self::Mythic),
+            _ => bail!("Expected a rarity among common, uncommon, rare and mythic, found `{s}`"),
+        }
+    }
+}
+
+/// Alchemy rebalanced cards are crafted separately from the card they are based
+/// on, and are only legal in Alchemy and Historic.
+#[derive(Hash, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CardVersion {
+    Original,
+    Rebalanced,
+}
+
+impl CardVersion {
+    const REBALANCED_PREFIX: &'static str = "A-";
+
+    #[must_use]
+    pub fn of(card_name: &str) -> Self {
+        if card_name.starts_with(Self::REBALANCED_PREFIX) {
+            Self::Rebalanced
+        } else {
+            Self::Original
+        }
+    }
+
+    #[must_use]
+    pub fn original_name(card_name: &str) -> &str {
+        card_name
+            .strip_prefix(Self::REBALANCED_PREFIX)
+            .unwrap_or(card_name)
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Hash, Clone, Serialize, Deserialize)]
 pub struct CardData {
     pub amount: u8,
@@ -113,9 +207,20 @@ pub struct Wildcards {
     pub uncommon: f32,
     pub rare: f32,
     pub mythic: f32,
+    #[serde(default)]
+    pub vault_progress: f32,
 }
 
 impl Wildcards {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let wildcards = if path.as_ref().exists() {
+            serde_json::from_reader(File::open(path)?).unwrap_or_default()
+        } else {
+            Self::default()
+        };
+        Ok(wildcards)
+    }
+
     #[must_use]
     pub fn select(&self, rarity: &Rarity) -> i32 {
         (match rarity {
@@ -141,6 +246,78 @@ impl Wildcards {
     }
 }
 
+/// Part of a deck a card is listed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Zone {
+    Companion,
+    Main,
+    Side,
+    /// Cards fetched from outside the game, listed apart from the sideboard
+    Wish,
+}
+
+/// Cards that fetch cards from outside the game, which makes the decks playing
+/// them need part of their sideboard even when it is ignored
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct WishCards(pub BTreeSet<String>);
+
+impl Default for WishCards {
+    fn default() -> Self {
+        Self(
+            [
+                "Karn, the Great Creator",
+                "Wish",
+                "Fae of Wishes // Granted",
+                "Mastermind's Acquisition",
+                "Burning Wish",
+                "Cunning Wish",
+                "Living Wish",
+                "Glittering Wish",
+                "Golden Wish",
+                "Death Wish",
+                "Coax from the Blind Eternities",
+                "Ring of Maruf",
+                "Spawnsire of Ulamog",
+            ]
+            .into_iter()
+            .map(str::to_owned)
+            .collect(),
+        )
+    }
+}
+
+impl WishCards {
+    /// How many cards a wish can fetch from a sideboard without a wishboard,
+    /// as in best-of-one
+    const SIDEBOARD_LIMIT: u8 = 7;
+
+    #[must_use]
+    pub fn contains(&self, card_name: &str) -> bool {
+        self.0.contains(card_name)
+    }
+}
+
+/// Which sideboard cards count as part of a deck
+#[derive(Debug, Clone, Copy)]
+pub enum Sideboard<'w> {
+    /// The whole sideboard, as in best-of-three
+    Counted,
+    /// Only the cards these wish cards can fetch, as in best-of-one
+    Ignored(&'w WishCards),
+}
+
+impl<'w> Sideboard<'w> {
+    #[must_use]
+    pub fn new(ignore_sideboard: bool, wish_cards: &'w WishCards) -> Self {
+        if ignore_sideboard {
+            Self::Ignored(wish_cards)
+        } else {
+            Self::Counted
+        }
+    }
+}
+
 #[derive(Hash, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Deck {
     pub name: String,
@@ -149,6 +326,21 @@ pub struct Deck {
     names_main: Vec<String>,
     amounts_side: Vec<u8>,
     names_side: Vec<String>,
+    /// Cards fetched from outside the game, listed apart from the sideboard
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    amounts_wish: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    names_wish: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    /// Deck of the Arena client this deck is synced with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    arena_id: Option<String>,
+    /// Deck this one is a budget version of, and the cards it plays instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    variant_of: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    swaps: Vec<Swap>,
 }
 
 impl std::fmt::Display for Deck {
@@ -166,6 +358,12 @@ impl std::fmt::Display for Deck {
                 writeln!(f, "{amount} {name}")?;
             }
         }
+        if !self.names_wish.is_empty() {
+            writeln!(f, "\nWishboard")?;
+            for (amount, name) in self.amounts_wish.iter().zip(self.names_wish.iter()) {
+                writeln!(f, "{amount} {name}")?;
+            }
+        }
         Ok(())
     }
 }
@@ -177,12 +375,15 @@ impl FromStr for Deck {
             Companion,
             Main,
             Side,
+            Wish,
         }
         let mut parsing_mode = ParsingMode::Main;
         let mut amounts_main = Vec::new();
         let mut names_main = Vec::new();
         let mut amounts_side = Vec::new();
         let mut names_side = Vec::new();
+        let mut amounts_wish = Vec::new();
+        let mut names_wish = Vec::new();
         let mut companion = None;
         for (i, l) in s.lines().skip_while(|l| l.trim().is_empty()).enumerate() {
             match l.trim() {
@@ -194,10 +395,21 @@ impl FromStr for Deck {
                     parsing_mode = ParsingMode::Main;
                     continue;
                 }
-                "Sideboard" | "" => {
+                "Sideboard" => {
                     parsing_mode = ParsingMode::Side;
                     continue;
                 }
+                // Blank lines do not end the wishboard
+                "" => {
+                    if !matches!(parsing_mode, ParsingMode::Wish) {
+                        parsing_mode = ParsingMode::Side;
+                    }
+                    continue;
+                }
+                "Wishboard" => {
+                    parsing_mode = ParsingMode::Wish;
+                    continue;
+                }
                 _ => {}
             };
             let error_message = || {
@@ -222,6 +434,10 @@ impl FromStr for Deck {
                     amounts_side.push(num);
                     names_side.push(name);
                 }
+                ParsingMode::Wish => {
+                    amounts_wish.push(num);
+                    names_wish.push(name);
+                }
             }
         }
         Ok(Self {
@@ -230,7 +446,13 @@ impl FromStr for Deck {
             amounts_side,
             names_main,
             names_side,
+            amounts_wish,
+            names_wish,
             companion,
+            tags: Vec::new(),
+            arena_id: None,
+            variant_of: None,
+            swaps: Vec::new(),
         })
     }
 }
@@ -244,39 +466,266 @@ impl Deck {
         }
     }
 
+    /// Builds a deck from its cards, given as amounts and names
+    #[must_use]
+    pub fn from_parts(
+        name: &str,
+        companion: Option<String>,
+        main: Vec<(u8, String)>,
+        side: Vec<(u8, String)>,
+        arena_id: Option<String>,
+    ) -> Self {
+        let (amounts_main, names_main) = main.into_iter().unzip();
+        let (amounts_side, names_side) = side.into_iter().unzip();
+        Self {
+            name: name.to_owned(),
+            companion,
+            amounts_main,
+            names_main,
+            amounts_side,
+            names_side,
+            amounts_wish: Vec::new(),
+            names_wish: Vec::new(),
+            tags: Vec::new(),
+            arena_id,
+            variant_of: None,
+            swaps: Vec::new(),
+        }
+    }
+
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
         fs::read_to_string(path)?.parse()
     }
 
-    pub fn cards(&self, ignore_sideboard: bool) -> impl Iterator<Item = (&String, u8)> {
+    /// Cards of the deck with their total amount. Ignoring the sideboard
+    /// keeps the cards wishes can fetch: the whole wishboard, or without one,
+    /// the first 7 cards of the sideboard when the main deck plays a wish. The
+    /// companion always counts, once.
+    pub fn cards(&self, sideboard: Sideboard) -> impl Iterator<Item = (&String, u8)> {
         let mut cards_amounts = HashMap::new();
-        let mainboard_iterator = self.amounts_main.iter().zip(self.names_main.iter());
-        let has_wishboard = self
+        let (ignore_sideboard, has_wish) = match sideboard {
+            Sideboard::Counted => (false, false),
+            Sideboard::Ignored(wish_cards) => (
+                true,
+                self.names_main.iter().any(|name| wish_cards.contains(name)),
+            ),
+        };
+        let fetches_sideboard = has_wish && self.names_wish.is_empty();
+        let mut room = WishCards::SIDEBOARD_LIMIT;
+        let mut companion_counted = false;
+        for (zone, card_name, amount) in self.cards_by_zone() {
+            let counted = match zone {
+                Zone::Companion => continue,
+                Zone::Main | Zone::Wish => amount,
+                Zone::Side if !ignore_sideboard => amount,
+                Zone::Side if fetches_sideboard => {
+                    let fetchable = amount.min(room);
+                    room -= fetchable;
+                    fetchable
+                }
+                Zone::Side => 0,
+            };
+            if counted == 0 {
+                continue;
+            }
+            // Arena lists the companion in the sideboard too, as the same copy
+            companion_counted |= zone != Zone::Main && self.companion.as_ref() == Some(card_name);
+            *cards_amounts.entry(card_name).or_insert(0) += counted;
+        }
+        if let Some(companion) = self.companion.as_ref().filter(|_| !companion_counted) {
+            *cards_amounts.entry(companion).or_insert(0) += 1;
+        }
+
+        cards_amounts.into_iter()
+    }
+
+    /// Every line of the deck, with the zone it is listed in
+    pub fn cards_by_zone(&self) -> impl Iterator<Item = (Zone, &String, u8)> {
+        fn zone<'d>(
+            zone: Zone,
+            amounts: &'d [u8],
+            names: &'d [String],
+        ) -> impl Iterator<Item = (Zone, &'d String, u8)> {
+            names
+                .iter()
+                .zip(amounts)
+                .map(move |(name, amount)| (zone, name, *amount))
+        }
+        self.companion
+            .iter()
+            .map(|name| (Zone::Companion, name, 1))
+            .chain(zone(Zone::Main, &self.amounts_main, &self.names_main))
+            .chain(zone(Zone::Side, &self.amounts_side, &self.names_side))
+            .chain(zone(Zone::Wish, &self.amounts_wish, &self.names_wish))
+    }
+
+    /// Every card name of the deck, in all of its zones
+    pub fn card_names(&self) -> impl Iterator<Item = &String> {
+        self.cards_by_zone().map(|(_, name, _)| name)
+    }
+
+    #[must_use]
+    pub fn companion(&self) -> Option<&str> {
+        self.companion.as_deref()
+    }
+
+    pub fn rename_card(&mut self, from: &str, to: &str) {
+        let names = self
             .names_main
-            .contains(&"Karn, the Great Creator".to_owned());
-        for (amount, card_name) in mainboard_iterator {
-            *cards_amounts.entry(card_name).or_insert(0) += amount;
+            .iter_mut()
+            .chain(self.names_side.iter_mut())
+            .chain(self.names_wish.iter_mut())
+            .chain(self.companion.iter_mut());
+        for name in names.filter(|name| *name == from) {
+            to.clone_into(name);
         }
-        if !ignore_sideboard {
-            let sideboard_iterator = self.amounts_side.iter().zip(self.names_side.iter());
-            for (amount, card_name) in sideboard_iterator {
-                *cards_amounts.entry(card_name).or_insert(0) += amount;
-            }
+    }
+
+    /// The rebalanced cards played by the deck, which restrict it to Alchemy
+    /// and Historic
+    pub fn rebalanced_cards(&self) -> impl Iterator<Item = &String> {
+        self.card_names()
+            .filter(|name| CardVersion::of(name) == CardVersion::Rebalanced)
+            .unique()
+    }
+
+    pub fn tags(&self) -> impl Iterator<Item = &String> {
+        self.tags.iter()
+    }
+
+    #[must_use]
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    pub fn add_tag(&mut self, tag: &str) {
+        if !self.has_tag(tag) {
+            self.tags.push(tag.to_owned());
         }
-        if ignore_sideboard && has_wishboard {
-            let sideboard_iterator = self.amounts_side.iter().zip(self.names_side.iter());
-            for (amount, card_name) in sideboard_iterator.take(7) {
-                *cards_amounts.entry(card_name).or_insert(0) += amount;
+    }
+
+    pub fn remove_tag(&mut self, tag: &str) {
+        self.tags.retain(|t| t != tag);
+    }
+
+    /// Keeps the tags, Arena link and budget swaps of `other`, for when a deck
+    /// is replaced by a new version
+    #[must_use]
+    pub fn with_metadata_of(self, other: &Self) -> Self {
+        Self {
+            tags: other.tags.clone(),
+            arena_id: other.arena_id.clone(),
+            variant_of: other.variant_of.clone(),
+            swaps: other.swaps.clone(),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn arena_id(&self) -> Option<&str> {
+        self.arena_id.as_deref()
+    }
+
+    /// A copy of the deck playing other cards, linked to it. The copy is not
+    /// synced with Arena.
+    #[must_use]
+    pub fn variant(&self, name: &str, swaps: Vec<Swap>) -> Self {
+        fn swap_in(amounts: &mut Vec<u8>, names: &mut Vec<String>, swap: &Swap, left: &mut u8) {
+            let Some(i) = names.iter().position(|name| *name == swap.from) else {
+                return;
+            };
+            if *left == 0 {
+                return;
             }
+            let copies = amounts[i].min(*left);
+            *left -= copies;
+            amounts[i] -= copies;
+            if amounts[i] == 0 {
+                amounts.remove(i);
+                names.remove(i);
+            }
+            if let Some(j) = names.iter().position(|name| *name == swap.to) {
+                amounts[j] += copies;
+            } else {
+                amounts.push(copies);
+                names.push(swap.to.clone());
+            }
+        }
+        let mut variant = Self {
+            name: name.to_owned(),
+            arena_id: None,
+            variant_of: Some(self.name.clone()),
+            ..self.clone()
+        };
+        for swap in &swaps {
+            let mut left = swap.amount;
+            swap_in(
+                &mut variant.amounts_main,
+                &mut variant.names_main,
+                swap,
+                &mut left,
+            );
+            swap_in(
+                &mut variant.amounts_side,
+                &mut variant.names_side,
+                swap,
+                &mut left,
+            );
+            swap_in(
+                &mut variant.amounts_wish,
+                &mut variant.names_wish,
+                swap,
+                &mut left,
+            );
         }
+        variant.swaps = swaps;
+        variant
+    }
 
-        cards_amounts.into_iter()
+    #[must_use]
+    pub fn variant_of(&self) -> Option<&str> {
+        self.variant_of.as_deref()
+    }
+
+    pub fn swaps(&self) -> impl Iterator<Item = &Swap> {
+        self.swaps.iter()
     }
 
-    pub fn contains(&self, s: &impl PartialEq<String>, ignore_sideboard: bool) -> bool {
-        (!ignore_sideboard && self.names_side.iter().any(|ns| s.eq(ns)))
-            || self.names_main.iter().any(|nm| s.eq(nm))
+    /// Whether both decks play the same cards in the same zones, regardless of
+    /// their names, tags and the order of their lines
+    #[must_use]
+    pub fn same_list(&self, other: &Self) -> bool {
+        fn zone<'d>(amounts: &[u8], names: &'d [String]) -> Vec<(&'d str, u8)> {
+            names
+                .iter()
+                .zip(amounts)
+                .map(|(name, amount)| (name.as_str(), *amount))
+                .sorted()
+                .collect()
+        }
+        self.companion == other.companion
+            && zone(&self.amounts_main, &self.names_main)
+                == zone(&other.amounts_main, &other.names_main)
+            && zone(&self.amounts_side, &self.names_side)
+                == zone(&other.amounts_side, &other.names_side)
+            && zone(&self.amounts_wish, &self.names_wish)
+                == zone(&other.amounts_wish, &other.names_wish)
     }
+
+    /// Whether the deck plays a card, counting the same cards as `cards`
+    pub fn contains(&self, s: &impl PartialEq<String>, sideboard: Sideboard) -> bool {
+        self.cards(sideboard).any(|(name, _)| s.eq(name))
+    }
+}
+
+/// What syncing a deck from the Arena client did to the roster
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncOutcome {
+    Added,
+    /// A deck of the same name is now linked to the Arena deck
+    Linked,
+    Updated,
+    Unchanged,
 }
 
 #[derive(Debug)]
@@ -366,10 +815,97 @@ impl Roster {
         Ok(())
     }
 
-    pub fn cards(&self, ignore_sideboard: bool) -> impl Iterator<Item = (&String, u8)> {
+    /// Adds decks from another roster. Decks we already have are left alone,
+    /// apart from their tags, and `on_conflict` decides what happens to decks
+    /// whose name is taken by a different list.
+    pub fn merge(&mut self, decks: Vec<Deck>, on_conflict: OnConflict) -> MergeSummary {
+        let mut summary = MergeSummary::default();
+        for deck in decks {
+            let imported = deck.name.clone();
+            let outcome = if let Some(local) = self.decks_mut().find(|local| local.same_list(&deck))
+            {
+                for tag in deck.tags() {
+                    local.add_tag(tag);
+                }
+                MergeOutcome::Identical {
+                    imported,
+                    local: local.name.clone(),
+                }
+            } else if let Ok(local) = self.find_mut(&imported) {
+                match on_conflict {
+                    OnConflict::Rename => {
+                        let renamed = self.free_name(&imported);
+                        self.add_deck(deck.name(&renamed));
+                        MergeOutcome::Renamed { imported, renamed }
+                    }
+                    OnConflict::Skip => MergeOutcome::Skipped(imported),
+                    OnConflict::Replace => {
+                        let tags = deck.tags().cloned().collect::<Vec<_>>();
+                        *local = deck.with_metadata_of(local);
+                        for tag in &tags {
+                            local.add_tag(tag);
+                        }
+                        MergeOutcome::Replaced(imported)
+                    }
+                }
+            } else {
+                self.add_deck(deck);
+                MergeOutcome::Added(imported)
+            };
+            summary.outcomes.push(outcome);
+        }
+        summary
+    }
+
+    /// Updates the deck linked to the same Arena deck, or else the unlinked deck
+    /// of the same name, and adds the deck if there is none, renamed if a deck
+    /// linked to another Arena deck has its name. Local names and tags are kept.
+    pub fn sync(&mut self, deck: Deck) -> SyncOutcome {
+        let linked = self
+            .decks()
+            .position(|local| local.arena_id.is_some() && local.arena_id == deck.arena_id)
+            .map(|i| (i, SyncOutcome::Updated));
+        let found = linked.or_else(|| {
+            self.decks()
+                .position(|local| local.arena_id.is_none() && local.name == deck.name)
+                .map(|i| (i, SyncOutcome::Linked))
+        });
+        let Some((i, outcome)) = found else {
+            let deck = if self.find(&deck.name).is_ok() {
+                let name = self.free_name(&deck.name);
+                deck.name(&name)
+            } else {
+                deck
+            };
+            self.add_deck(deck);
+            return SyncOutcome::Added;
+        };
+        let local = &mut self.decks[i];
+        if outcome == SyncOutcome::Updated && local.same_list(&deck) {
+            return SyncOutcome::Unchanged;
+        }
+        let tags = mem::take(&mut local.tags);
+        *local = Deck {
+            name: local.name.clone(),
+            tags,
+            ..deck
+        };
+        outcome
+    }
+
+    /// The first of `name (2)`, `name (3)`... that no deck uses
+    fn free_name(&self, name: &str) -> String {
+        // With n decks, one of n + 1 names is free
+        (2..=self.len() + 2)
+            .map(|i| format!("{name} ({i})"))
+            .find(|candidate| self.find(candidate).is_err())
+            .expect("There are fewer decks than names to try")
+    }
+
+    pub fn cards<'r>(&'r self, sideboard: Sideboard<'r>) -> impl Iterator<Item = (&'r String, u8)> {
         self.decks
             .iter()
-            .flat_map(move |deck| deck.cards(ignore_sideboard))
+            .flat_map(move |deck| deck.cards(sideboard))
     }
 }
 
@@ -385,6 +921,8 @@ pub struct Inventory {
     collection: Collection,
     collection_path: PathBuf,
     coeffs: WildcardCoefficients,
+    vault_progress: f32,
+    wish_cards: WishCards,
 }
 
 impl Inventory {
@@ -401,106 +939,51 @@ impl Inventory {
         }
         let collection: Collection = serde_json::from_reader(File::open(&collection_path)?)
             .with_context(|| format!("Failed to open collection with path {collection_path:?}"))?;
-        let wildcards: Wildcards = if wildcards_path.as_ref().exists() {
-            serde_json::from_reader(File::open(&wildcards_path)?).unwrap_or_default()
-        } else {
-            Wildcards::default()
-        };
+        let wildcards = Wildcards::open(wildcards_path)?;
         let coeffs = wildcards.coefficients();
         Ok(Self {
             collection,
             coeffs,
+            vault_progress: wildcards.vault_progress,
             collection_path: collection_path.as_ref().to_path_buf(),
+            wish_cards: WishCards::default(),
         })
     }
 
-    pub fn card_cost(&self, card_name: &str) -> Result<f32> {
-        let cheapest_rarity = &self.cheapest_rarity(card_name)?;
-        let cost = self.coeffs.select(cheapest_rarity);
-        Ok(cost)
-    }
-
-    /// This function computes the importance of a card, with regard to how many
-    /// copies a deck plays.
-    pub fn card_cost_considering_deck(
-        &mut self,
-        card_name: &str,
-        in_deck_amount: u8,
-    ) -> Result<f32> {
-        let in_collection_amount = self.card_amount(card_name)?;
-        let missing = in_deck_amount.saturating_sub(in_collection_amount);
-        if missing == 0 {
-            Ok(0.0)
-        } else {
-            let tiebreaker_bonus = 4.0 / f32::from(missing);
-            Ok((self.card_cost(card_name)?).mul_add(f32::from(missing), tiebreaker_bonus))
-        }
-    }
-
-    #[allow(clippy::missing_panics_doc)]
-    pub fn cheapest_rarity(&self, card_name: &str) -> Result<Rarity> {
-        let card_group = self.collection.get(card_name)?;
-        let group_rarities = card_group.iter().map(|(_, rarity, _)| rarity).collect_vec();
-        let ordered_rarities = self.coeffs.order();
-        let cheapest_rarity = ordered_rarities
-            .iter()
-            .find(|r| group_rarities.contains(r))
-            .unwrap();
-        Ok(*cheapest_rarity)
-    }
-
-    #[allow(clippy::missing_panics_doc)]
-    pub fn cheapest_version<'a>(&'a self, card_name: &'a str) -> Result<&(u8, Rarity, String)> {
-        let cheapest_rarity = self.cheapest_rarity(card_name)?;
-        let card_group = self.collection.get(card_name)?;
-        let cheapest_version = card_group
-            .iter()
-            .find(|(_, rarity, _)| *rarity == cheapest_rarity)
-            .unwrap();
-        Ok(cheapest_version)
-    }
-
-    pub fn card_amount(&self, card_name: &str) -> Result<u8> {
-        let in_collection: u8 = self
-            .collection
-            .get(card_name)?
-            .iter()
-            .map(|(amount, _, _)| *amount)
-            .sum();
-        Ok(in_collection.clamp(0, 4))
-    }
-
-    pub fn deck_cost(&mut self, deck: &Deck, ignore_sideboard: bool) -> Result<f32> {
-        let mut result = 0.0;
-        for (card_name, amount) in deck.cards(ignore_sideboard) {
-            let missing = amount.saturating_sub(self.card_amount(card_name)?);
-            result += f32::from(missing) * self.card_cost(card_name)?;
-        }
-        if result.abs() < f32::EPSILON {
-            return Ok(0.0);
-        }
-        let closeness_bound = self.rare_coeff().mul_add(4.0, self.mythic_coeff());
-        let cool_formula = f32::max(result - closeness_bound, 1.00);
-        Ok(cool_formula)
+    /// Counts the sideboard cards these fetch when the sideboard is ignored
+    #[must_use]
+    pub fn with_wish_cards(mut self, wish_cards: WishCards) -> Self {
+        self.wish_cards = wish_cards;
+        self
     }
 
     pub fn update_collection(&mut self, recently_fetched: Collection, roster: &Roster) {
+        self.collection.separate_rebalanced();
         self.collection.ensure_known(roster);
         let mut original = mem::take(&mut self.collection);
         original.merge(recently_fetched);
         mem::swap(&mut original, &mut self.collection);
     }
 
+    #[must_use]
+    pub fn collection(&self) -> &Collection {
+        &self.collection
+    }
+
     pub fn get<'b>(&'b mut self, s: &'b str) -> Result<&Vec<(u8, Rarity, String)>> {
         self.collection.get(s)
     }
 
-    pub fn missing_cards<'b>(
-        &'b self,
-        deck: &'b Deck,
-        ignore_sideboard: bool,
-    ) -> Result<Vec<(&String, u8, Rarity, &String)>> {
-        self.collection.missing(deck, ignore_sideboard)
+    /// Makes sure every card of `deck` is known, fixing names that differ only
+    /// in case or punctuation. Misspelled names are replaced by their closest
+    /// match when `auto_fix` is set. Returns the replacements that were made,
+    /// or fails with the names that could not be resolved.
+    pub fn resolve_names(
+        &mut self,
+        deck: &mut Deck,
+        auto_fix: bool,
+    ) -> Result<Vec<(String, String)>> {
+        self.collection.resolve_names(deck, auto_fix)
     }
 
     #[must_use]
@@ -508,6 +991,12 @@ impl Inventory {
         &self.coeffs
     }
 
+    /// Current vault progress, in percent
+    #[must_use]
+    pub fn vault_progress(&self) -> f32 {
+        self.vault_progress
+    }
+
     #[must_use]
     pub fn common_coeff(&self) -> f32 {
         self.coeffs.common
@@ -529,6 +1018,24 @@ impl Inventory {
     }
 }
 
+impl CostModel for Inventory {
+    fn prints(&self, card_name: &str) -> Result<&Vec<(u8, Rarity, String)>> {
+        self.collection.get(card_name)
+    }
+
+    fn rarity_cost(&self, rarity: Rarity) -> f32 {
+        self.coeffs.select(&rarity)
+    }
+
+    fn rarity_order(&self) -> [Rarity; 5] {
+        self.coeffs.order()
+    }
+
+    fn wish_cards(&self) -> &WishCards {
+        &self.wish_cards
+    }
+}
+
 impl Drop for Inventory {
     fn drop(&mut self) {
         fs::write(
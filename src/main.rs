@@ -1,18 +1,38 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use clap::{arg, Parser, Subcommand};
 use detr::{
-    card_getter::CardGetter, collection::Collection, craft_suggester::CraftRecommender,
-    mtga_id_translator::MtgaIdTranslator, Deck, Inventory, Rarity, Roster, Wildcards,
+    analysis::{card_data_of, CardType, DeckAnalysis},
+    booster::PackModel,
+    budget,
+    bundle::{OnConflict, RosterBundle},
+    card_getter::CardGetter,
+    config::Config,
+    cost_model::{CostModel, DeckCostStrategy},
+    craft_suggester::CraftRecommender,
+    decklist_parser::parse_decklist,
+    manabase::ManaBase,
+    match_history::{self, DeckStats, GameRecord, MatchHistory},
+    mtga_id_translator::{MtgaIdTranslator, NetCardData},
+    odds::{self, CardGroup},
+    player_log::PlayerLog,
+    server::Api,
+    sets::{self, Date, SetCalendar},
+    simulation::{self, KeepRule, LandRange},
+    suggestions::{self, suggestions, Suggestion, SuggestionFilter},
+    tui::{self, App},
+    validation::validate,
+    Deck, Inventory, Rarity, Roster, SyncOutcome, Wildcards,
 };
 use directories::BaseDirs;
 use either::{Left, Right};
 use itertools::Itertools;
 use mktemp::Temp;
+use rand::{rngs::StdRng, SeedableRng};
 use regex::Regex;
 use std::{
     collections::HashMap,
     fs::{self},
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::{Command, Stdio},
 };
 
@@ -55,15 +75,50 @@ enum Commands {
 
         #[arg(long, short)]
         names: Option<Vec<String>>,
+
+        #[arg(long, help = "Replace misspelled card names by their closest match")]
+        auto_fix: bool,
     },
     Paste {
         name: String,
+
+        #[arg(long, help = "Replace misspelled card names by their closest match")]
+        auto_fix: bool,
     },
     Missing {
         deck_name: String,
     },
     #[command(alias = "u")]
-    UpdateCollection,
+    UpdateCollection {
+        #[arg(
+            long,
+            help = "Read the collection, wildcards and vault progress from the game log instead of the daemon"
+        )]
+        log: bool,
+        #[arg(
+            long,
+            requires = "log",
+            help = "Path of the game log, if it is not in the game's data directory"
+        )]
+        log_path: Option<PathBuf>,
+    },
+    #[command(about = "Add or update the decks built in Arena, from the game log")]
+    SyncArenaDecks {
+        #[arg(
+            long,
+            help = "Path of the game log, if it is not in the game's data directory"
+        )]
+        log_path: Option<PathBuf>,
+    },
+    #[command(about = "Win rates of the decks, recording the games of the game log first")]
+    Stats {
+        deck_name: Option<String>,
+        #[arg(
+            long,
+            help = "Path of the game log, if it is not in the game's data directory"
+        )]
+        log_path: Option<PathBuf>,
+    },
     Remove {
         deck_name: String,
     },
@@ -76,6 +131,81 @@ enum Commands {
     Edit {
         deck_name: String,
     },
+    #[command(about = "Mana curve, land count, card types and color pips of a deck")]
+    Analyze {
+        deck_name: String,
+    },
+    #[command(about = "Colored sources of the lands of a deck, against the needs of its spells")]
+    Manabase {
+        deck_name: String,
+    },
+    #[command(about = "Odds to draw cards of the main deck, or lands in the opening hand")]
+    Odds {
+        deck_name: String,
+        #[arg(
+            long = "cards",
+            short,
+            help = "Cards that count as a hit, separated with `|`. Repeat it to need hits of every group"
+        )]
+        groups: Vec<CardGroup>,
+        #[arg(long, default_value_t = 1, help = "Hits needed in each group")]
+        at_least: u32,
+        #[arg(long, default_value_t = 4, help = "Last turn to show")]
+        turns: u32,
+    },
+    #[command(about = "Draw a random opening hand from a deck")]
+    Draw {
+        deck_name: String,
+        #[arg(long, help = "Seed for the shuffle")]
+        seed: Option<u64>,
+    },
+    #[command(about = "Simulate mulligans and land drops of a deck")]
+    Simulate {
+        deck_name: String,
+        #[arg(
+            long,
+            short,
+            default_value_t = 10_000,
+            help = "Number of games simulated"
+        )]
+        trials: u32,
+        #[arg(long, help = "Seed for the simulation")]
+        seed: Option<u64>,
+        #[arg(long, default_value = "2-4", help = "Land counts of the hands to keep")]
+        lands: LandRange,
+        #[arg(
+            long,
+            default_value_t = 5,
+            help = "Hand size at which any hand is kept"
+        )]
+        keep_at: u32,
+        #[arg(long, default_value_t = 6, help = "Last turn to show")]
+        turns: u32,
+        #[arg(long, help = "Simulate games on the draw instead of on the play")]
+        draw: bool,
+    },
+    #[command(
+        about = "Add a budget version of a deck, playing owned cards instead of missing rares"
+    )]
+    Budget {
+        deck_name: String,
+        #[arg(
+            long,
+            default_value_t = 0,
+            help = "Rares and mythics the budget version may still miss"
+        )]
+        max_rares: u32,
+        #[arg(
+            long,
+            help = "Name of the budget version, `<deck> (budget)` by default"
+        )]
+        name: Option<String>,
+    },
+    #[command(about = "Check deck sizes, copy limits and companion conditions")]
+    Validate {
+        #[arg(help = "Deck to check, every deck of the roster if omitted")]
+        deck_name: Option<String>,
+    },
     #[command(alias = "s")]
     Suggest {
         #[arg(
@@ -84,7 +214,44 @@ enum Commands {
             help = "Will not favour cards from decks that are close to completion"
         )]
         equally: bool,
+        #[arg(long, help = "Show how each deck contributes to the score of a card")]
+        explain: Option<String>,
+        #[arg(
+            long,
+            short = 'x',
+            help = "Show how each deck contributes to the score of every suggestion"
+        )]
+        expanded: bool,
+        #[arg(
+            long,
+            short = 'n',
+            default_value_t = 10,
+            help = "Number of suggestions shown per rarity"
+        )]
+        top: usize,
+        #[arg(long, default_value_t = 1, help = "Show the n-th page of suggestions")]
+        page: usize,
+        #[arg(
+            long,
+            value_delimiter = ',',
+            help = "Only show these rarities (c, u, r, m)"
+        )]
+        rarity: Vec<Rarity>,
+        #[arg(long, help = "Only suggest cards we would craft from this set")]
+        set: Option<String>,
+        #[arg(long, short, help = "Only take decks with this tag into account")]
+        tag: Option<String>,
     },
+    Tag {
+        deck_name: String,
+        tags: Vec<String>,
+        #[arg(long, short, help = "Remove the tags instead of adding them")]
+        remove: bool,
+    },
+    #[command(subcommand)]
+    NeverCraft(NeverCraft),
+    #[command(subcommand)]
+    Roster(RosterCommand),
     #[command(alias = "l")]
     List,
     Rename {
@@ -97,7 +264,41 @@ enum Commands {
         rare: u32,
         mythic: u32,
     },
-    Booster,
+    Booster {
+        #[arg(
+            long,
+            short,
+            default_value_t = 0,
+            help = "Number of Monte Carlo trials used to estimate the variance of each set"
+        )]
+        trials: usize,
+        #[arg(
+            long,
+            short,
+            default_value_t = 10,
+            help = "Number of packs opened per trial"
+        )]
+        packs: usize,
+        #[arg(long, help = "Seed for the Monte Carlo simulation")]
+        seed: Option<u64>,
+        #[arg(long, short, help = "Estimate the value of a draft instead of a pack")]
+        draft: bool,
+        #[arg(
+            long,
+            help = "Discount sets that leave Standard soon, by the time they have left"
+        )]
+        discount_rotating: bool,
+    },
+    #[command(about = "Release dates of the known sets, and when they leave Standard")]
+    Sets,
+    SetVault {
+        #[arg(help = "Vault progress, in percent")]
+        progress: f32,
+    },
+    Completion {
+        #[arg(help = "Only show this set")]
+        set: Option<String>,
+    },
     Which {
         query: String,
     },
@@ -111,6 +312,44 @@ enum Commands {
         with: Option<Vec<String>>,
     },
     PrintCoeffs,
+    #[command(about = "Browse decks, missing cards and suggestions interactively")]
+    Tui,
+    #[command(about = "Serve the roster as a JSON API on localhost")]
+    Serve {
+        #[arg(long, short, default_value_t = 8080)]
+        port: u16,
+    },
+}
+
+/// Cards that `suggest` will leave out
+#[derive(Subcommand)]
+enum NeverCraft {
+    Add { cards: Vec<String> },
+    Remove { cards: Vec<String> },
+    List,
+}
+
+/// Sharing decks between rosters
+#[derive(Subcommand)]
+enum RosterCommand {
+    Export {
+        file: PathBuf,
+        #[arg(help = "Decks to export, all of them if none are given")]
+        decks: Vec<String>,
+        #[arg(long, short, help = "Only export decks with this tag")]
+        tag: Option<String>,
+    },
+    Import {
+        file: PathBuf,
+        #[arg(
+            long,
+            default_value = "rename",
+            help = "What to do with decks whose name is taken by a different list: rename, skip or replace"
+        )]
+        on_conflict: OnConflict,
+        #[arg(long, help = "Replace misspelled card names by their closest match")]
+        auto_fix: bool,
+    },
 }
 
 fn export(deck_name: &str, roster: &Roster) -> Result<()> {
@@ -127,7 +366,7 @@ fn missing(
     ignore_sideboard: bool,
 ) -> Result<()> {
     let deck = roster.find(deck_name)?;
-    let missing_cards = inventory.missing_cards(deck, ignore_sideboard);
+    let missing_cards = inventory.missing(deck, ignore_sideboard);
 
     let mut missing_cards = missing_cards?;
     missing_cards.sort_by_key(|m| m.2);
@@ -150,70 +389,393 @@ fn missing(
         .iter()
         .filter(|m| m.1 > 0)
         .for_each(|missing| {
-            println!("{:?}\t {} {}", missing.2, missing.1, missing.0);
+            let companion = if deck.companion() == Some(missing.0.as_str()) {
+                " (companion)"
+            } else {
+                ""
+            };
+            println!("{:?}\t {} {}{companion}", missing.2, missing.1, missing.0);
         });
     Ok(())
 }
 
+fn explain(suggestion: &Suggestion) {
+    println!(
+        "{} ({:?}), score {:.2}, {:.2}% of the total",
+        suggestion.card, suggestion.rarity, suggestion.score, suggestion.share
+    );
+    println!("Missing\t Deck cost\t Tiebreaker\t Contribution\t Deck");
+    for contribution in &suggestion.contributions {
+        println!(
+            "{}\t {:.2}\t\t {:.2}\t\t {:.2}\t\t {}",
+            contribution.missing,
+            contribution.deck_cost,
+            contribution.tiebreaker_bonus,
+            contribution.score,
+            contribution.deck
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn suggest(
     roster: &Roster,
-    inventory: &mut Inventory,
+    inventory: &Inventory,
     ignore_sideboard: bool,
     equally: bool,
+    strategy: &DeckCostStrategy,
+    filter: SuggestionFilter,
+    explained_card: Option<&str>,
+    expanded: bool,
+    top: usize,
+    page: usize,
 ) -> Result<()> {
-    let mut sug_common = HashMap::new();
-    let mut sug_uncommon = HashMap::new();
-    let mut sug_rare = HashMap::new();
-    let mut sug_mythic = HashMap::new();
+    if !equally {
+        println!("Deck cost: {strategy}\n");
+    }
+    let suggestions = suggestions(
+        roster,
+        inventory,
+        ignore_sideboard,
+        (!equally).then_some(strategy),
+        filter,
+    )?;
 
-    for deck in roster.decks() {
-        for (card_name, deck_amount) in deck.cards(ignore_sideboard) {
-            let rarity = inventory
-                .cheapest_rarity(card_name)
-                .context("When computing rarity")?;
-            let deck_cost = if equally {
-                100.0
+    if let Some(card) = explained_card {
+        explain(suggestions::find(&suggestions, card)?);
+        return Ok(());
+    }
+
+    let rarities = [
+        (Rarity::Common, "Common"),
+        (Rarity::Uncommon, "Uncommon"),
+        (Rarity::Rare, "Rare"),
+        (Rarity::Mythic, "Mythic Rare"),
+    ];
+    let rarities = rarities
+        .into_iter()
+        .filter(|(rarity, _)| filter.rarities.is_empty() || filter.rarities.contains(rarity));
+    for (rarity, rarity_name) in rarities {
+        println!("{rarity_name}");
+        let of_rarity = suggestions.iter().filter(|s| s.rarity == rarity);
+        for suggestion in of_rarity.skip(top * page.saturating_sub(1)).take(top) {
+            if expanded {
+                explain(suggestion);
+                println!();
             } else {
-                inventory
-                    .deck_cost(deck, ignore_sideboard)
-                    .with_context(|| format!("Failed to compute deck cost for `{}`", deck.name))?
-            };
-            let selected_sugg = match rarity {
-                Rarity::Common => &mut sug_common,
-                Rarity::Uncommon => &mut sug_uncommon,
-                Rarity::Rare => &mut sug_rare,
-                Rarity::Mythic => &mut sug_mythic,
-                _ => continue,
+                println!("{:.2}%\t {}", suggestion.share, suggestion.card);
+            }
+        }
+        println!();
+    }
+    Ok(())
+}
+
+/// Sets leaving Standard are discounted when `rotating` is given
+#[allow(clippy::too_many_arguments)]
+fn booster(
+    config: &Config,
+    roster: &Roster,
+    inventory: &Inventory,
+    ignore_sideboard: bool,
+    trials: usize,
+    packs: usize,
+    seed: Option<u64>,
+    draft: bool,
+    rotating: Option<&SetCalendar>,
+) -> Result<()> {
+    let model = PackModel::new(config, roster, inventory, ignore_sideboard)?;
+    let today = Date::today();
+    let mut set_values = model
+        .sets()
+        .map(|set| {
+            let value = if draft {
+                model.draft_value(set)
+            } else {
+                model.expected_value(set)
             };
-            let sugg_coeff =
-                inventory.card_cost_considering_deck(card_name, deck_amount)? / deck_cost;
+            let share = rotating.map_or(1.0, |calendar| {
+                calendar.standard_share(set, today, config.rotation.discount_days)
+            });
+            (set, value * share)
+        })
+        .collect_vec();
+    set_values.sort_unstable_by(|(_, v1), (_, v2)| v2.partial_cmp(v1).unwrap());
+    let mut rng = StdRng::seed_from_u64(seed.unwrap_or_else(rand::random));
+    for (set, value) in set_values.iter().take(10) {
+        if trials == 0 || draft {
+            println!("{value:.2}\t {set}");
+        } else {
+            let simulation = model.simulate(set, packs, trials, &mut rng);
+            println!(
+                "{value:.2}\t {:.2} ± {:.2}\t {set}",
+                simulation.mean, simulation.std_dev
+            );
+        }
+    }
+    Ok(())
+}
 
-            *selected_sugg.entry(card_name).or_insert(0.0) += sugg_coeff;
+fn completion(inventory: &Inventory, set: Option<&str>) {
+    println!("Vault progress: {:.1}%\n", inventory.vault_progress());
+    let completion = inventory.collection().completion();
+    let sets = completion
+        .keys()
+        .map(|(set, _)| *set)
+        .filter(|s| set.is_none() || set == Some(*s))
+        .unique()
+        .sorted();
+    for set in sets {
+        let rarities = [
+            Rarity::Common,
+            Rarity::Uncommon,
+            Rarity::Rare,
+            Rarity::Mythic,
+        ]
+        .iter()
+        .filter_map(|rarity| {
+            let c = completion.get(&(set, *rarity))?;
+            Some(format!("{rarity:?} {}/{}", c.playsets, c.known))
+        })
+        .join(", ");
+        println!("{set}\t {rarities}");
+    }
+}
+
+fn resolve_names(deck: &mut Deck, inventory: &mut Inventory, auto_fix: bool) -> Result<()> {
+    let fixes = inventory
+        .resolve_names(deck, auto_fix)
+        .with_context(|| format!("Failed to resolve card names of `{}`", deck.name))?;
+    for (from, to) in fixes {
+        println!("Replaced `{from}` with `{to}` in `{}`", deck.name);
+    }
+    Ok(())
+}
+
+fn print_land_odds(deck: &Deck, analysis: &DeckAnalysis) {
+    let library = odds::library_size(deck);
+    let lands = analysis.main.lands();
+    println!("{library} cards, {lands} lands\n\nLands in the opening hand");
+    for hits in 0..=odds::OPENING_HAND {
+        let probability = odds::exactly(library, lands, odds::OPENING_HAND, hits);
+        println!("  {hits}\t {:.1}%", probability * 100.0);
+    }
+    let keepable: f64 = (2..=4)
+        .map(|hits| odds::exactly(library, lands, odds::OPENING_HAND, hits))
+        .sum();
+    println!("2 to 4 lands\t {:.1}%", keepable * 100.0);
+    if analysis.main.unknown > 0 {
+        eprintln!(
+            "Warning: {} cards have no card data, and do not count as lands",
+            analysis.main.unknown
+        );
+    }
+}
+
+fn print_draw_odds(deck: &Deck, groups: &[CardGroup], at_least: u32, turns: u32) -> Result<()> {
+    let library = odds::library_size(deck);
+    let sizes = odds::group_sizes(deck, groups)?;
+    for (i, (group, size)) in groups.iter().zip(&sizes).enumerate() {
+        let and = if i == 0 { "Drawing" } else { "and" };
+        println!("{and} at least {at_least} of {group} ({size} cards)");
+    }
+    let groups = sizes.into_iter().map(|size| (size, at_least)).collect_vec();
+    println!("\nTurn\t On the play\t On the draw");
+    for turn in 1..=turns {
+        let [play, draw] = [true, false]
+            .map(|on_the_play| odds::all_of(library, &groups, odds::cards_seen(turn, on_the_play)));
+        println!("{turn}\t {:.1}%\t\t {:.1}%", play * 100.0, draw * 100.0);
+    }
+    Ok(())
+}
+
+fn draw(deck: &Deck, seed: Option<u64>) {
+    let seed = seed.unwrap_or_else(rand::random);
+    let library = simulation::library(deck);
+    let mut hand = simulation::draw_hand(&library, &mut StdRng::seed_from_u64(seed));
+    hand.sort();
+    println!("Opening hand of `{}`, seed {seed}", deck.name);
+    for card in hand {
+        println!("  {card}");
+    }
+}
+
+fn simulate(
+    deck: &Deck,
+    translator: &mut MtgaIdTranslator,
+    trials: u32,
+    seed: Option<u64>,
+    rule: &KeepRule,
+    turns: u32,
+    on_the_play: bool,
+) {
+    let card_data = card_data_of(deck, translator);
+    let lands = simulation::library(deck)
+        .into_iter()
+        .map(|name| {
+            card_data
+                .get(name)
+                .is_some_and(|data| CardType::of(&data.type_line) == CardType::Land)
+        })
+        .collect_vec();
+    let seed = seed.unwrap_or_else(rand::random);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let simulation = simulation::simulate(&lands, rule, turns, on_the_play, trials, &mut rng);
+    println!("Seed {seed}\n{simulation}");
+}
+
+fn budget(
+    deck_name: &str,
+    variant_name: Option<String>,
+    max_rares: u32,
+    config: &Config,
+    roster: &mut Roster,
+    inventory: &Inventory,
+    translator: &mut MtgaIdTranslator,
+) -> Result<()> {
+    let deck = roster.find(deck_name)?.clone();
+    let card_data = card_data_of(&deck, translator);
+    let pool = translator
+        .cards()
+        .filter(|card| {
+            inventory
+                .card_amount(&card.name)
+                .is_ok_and(|amount| amount > 0)
+        })
+        .unique_by(|card| &card.name)
+        .collect_vec();
+    let plan = budget::plan(
+        &deck,
+        inventory,
+        &card_data,
+        &pool,
+        &config.substitutes,
+        max_rares,
+    )?;
+    if plan.swaps.is_empty() {
+        if plan.missing_rares <= max_rares {
+            println!(
+                "`{deck_name}` misses {} rares and mythics, no budget version is needed",
+                plan.missing_rares
+            );
+            return Ok(());
         }
+        bail!("No owned card can replace the missing rares and mythics of `{deck_name}`");
     }
 
-    let suggestions = vec![
-        (sug_common, "Common"),
-        (sug_uncommon, "Uncommon"),
-        (sug_rare, "Rare"),
-        (sug_mythic, "Mythic Rare"),
-    ];
-    for (suggestions, rarity) in suggestions {
-        println!("{rarity}");
-        let mut suggestions: Vec<(&String, f32)> = suggestions.into_iter().collect();
-        suggestions.sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-        for (card_name, sugg_coeff) in suggestions.iter().take(10) {
-            println!("{sugg_coeff:.2} {card_name}");
+    let variant_name = variant_name.unwrap_or_else(|| format!("{deck_name} (budget)"));
+    let variant = deck.variant(&variant_name, plan.swaps);
+    for swap in variant.swaps() {
+        println!("{swap}");
+    }
+    match roster.find(&variant_name) {
+        Ok(existing) if existing.variant_of() != Some(deck_name) => {
+            bail!("`{variant_name}` already exists, and is not a budget version of `{deck_name}`")
         }
-        println!();
+        Ok(_) => roster.replace(&variant_name, variant)?,
+        Err(_) => roster.add_deck(variant),
+    }
+    println!("Saved `{variant_name}`");
+    if plan.missing_rares > max_rares {
+        eprintln!(
+            "Warning: `{variant_name}` still misses {} rares and mythics",
+            plan.missing_rares
+        );
     }
     Ok(())
 }
 
+/// Warns about the Standard decks that lose cards to the next rotation
+fn warn_rotating(config: &Config, roster: &Roster, inventory: &Inventory, calendar: &SetCalendar) {
+    let today = Date::today();
+    let Some(rotation) = calendar.next_rotation(today) else {
+        eprintln!("Warning: no upcoming Standard rotation in sets.json, add the next one to be warned about it");
+        return;
+    };
+    if today.days_until(rotation.date) > i64::from(config.rotation.warning_days) {
+        return;
+    }
+    for deck in roster.decks() {
+        let rotating = match sets::rotating_cards(deck, inventory, calendar, today, rotation) {
+            Ok(rotating) => rotating,
+            Err(err) => {
+                eprintln!("Warning: skipped `{}`: {err:#}", deck.name);
+                continue;
+            }
+        };
+        if !rotating.cards.is_empty() {
+            eprintln!(
+                "Warning: `{}` loses {} to the rotation of {}",
+                deck.name,
+                rotating.cards.iter().join(", "),
+                rotation.date
+            );
+        }
+        if !rotating.unchecked.is_empty() {
+            eprintln!(
+                "Warning: `{}`: the sets of {} are missing from sets.json, so their rotation was not checked",
+                deck.name,
+                rotating.unchecked.iter().join(", ")
+            );
+        }
+    }
+}
+
+fn list_sets(calendar: &SetCalendar) {
+    let today = Date::today();
+    println!("Set\t Released\t Leaves Standard\t Name");
+    for set in calendar.sets.iter().sorted_by_key(|set| set.released) {
+        let rotation = match calendar.rotation_of(&set.code) {
+            Some(date) => date.to_string(),
+            None if calendar.is_standard(&set.code, today) => "not scheduled".to_owned(),
+            None => "-".to_owned(),
+        };
+        println!(
+            "{}\t {}\t {rotation:<15}\t {}",
+            set.code, set.released, set.name
+        );
+    }
+    if calendar.next_rotation(today).is_none() {
+        println!("\nNo upcoming rotation is scheduled. Add the next one to sets.json once it is announced.");
+    }
+}
+
+/// Card data to check the companion condition with, only fetched for decks
+/// with a companion
+fn companion_card_data(
+    deck: &Deck,
+    translator: &mut MtgaIdTranslator,
+) -> HashMap<String, NetCardData> {
+    if deck.companion().is_some() {
+        card_data_of(deck, translator)
+    } else {
+        HashMap::new()
+    }
+}
+
+fn warn_if_invalid(deck: &Deck, translator: &mut MtgaIdTranslator) {
+    for problem in validate(deck, &companion_card_data(deck, translator)) {
+        eprintln!("Warning: `{}`: {problem}", deck.name);
+    }
+}
+
+/// Reads a decklist in any of the formats `parse_decklist` knows
+fn parse_deck(text: &str, name: &str) -> Result<Deck> {
+    let parsed = parse_decklist(text)?;
+    println!("Read `{name}` as {}", parsed.dialect);
+    for line in &parsed.ignored {
+        eprintln!("Warning: ignored line `{line}` of `{name}`");
+    }
+    Ok(parsed.deck.name(name))
+}
+
 fn add_from_file(
     deck_paths: &[String],
     names: Option<&Vec<String>>,
     roster: &mut Roster,
+    inventory: &mut Inventory,
+    translator: &mut MtgaIdTranslator,
+    auto_fix: bool,
 ) -> Result<()> {
     let names_iter = match names {
         Some(names) => Left(names.iter().map(std::string::String::as_str)),
@@ -223,20 +785,186 @@ fn add_from_file(
         .iter()
         .zip(names_iter)
         .map(|(deck_path, name)| {
-            let deck = fs::read_to_string(deck_path)
-                .context("Failed to find decklist")?
-                .parse::<Deck>()
-                .context("Failed to parse decklist")?
-                .name(name);
-            Ok(deck)
+            let text = fs::read_to_string(deck_path).context("Failed to find decklist")?;
+            parse_deck(&text, name).context("Failed to parse decklist")
         })
         .collect::<anyhow::Result<Vec<Deck>>>()?;
-    for deck in decks {
+    for mut deck in decks {
+        resolve_names(&mut deck, inventory, auto_fix)?;
+        warn_if_invalid(&deck, translator);
         roster.add_deck(deck);
     }
     Ok(())
 }
 
+/// Reads the game log, warning about the replies that had to be skipped
+fn read_player_log(path: &Path) -> Result<PlayerLog> {
+    let log = PlayerLog::from_file(path)?;
+    for reason in &log.skipped {
+        eprintln!("Warning: skipped a reply of the game log: {reason}");
+    }
+    Ok(log)
+}
+
+fn sync_arena_decks(
+    log_path: Option<PathBuf>,
+    translator: &mut MtgaIdTranslator,
+    roster: &mut Roster,
+    inventory: &mut Inventory,
+) -> Result<()> {
+    let log_path = log_path
+        .or_else(PlayerLog::default_path)
+        .context("Could not find the game log, pass its path to --log-path")?;
+    let log = read_player_log(&log_path)?;
+    if log.decks.is_empty() {
+        bail!(
+            "Found no deck in {}, enable detailed logs in the options of the game and restart it",
+            log_path.display()
+        );
+    }
+    let mut changed = 0;
+    for arena_deck in &log.decks {
+        let mut deck = match CardGetter::arena_deck(translator, arena_deck) {
+            Ok(deck) => deck,
+            Err(err) => {
+                eprintln!("Warning: skipped `{}`: {err:#}", arena_deck.name);
+                continue;
+            }
+        };
+        if let Err(err) = resolve_names(&mut deck, inventory, false) {
+            eprintln!("Warning: skipped `{}`: {err:#}", arena_deck.name);
+            continue;
+        }
+        let name = &arena_deck.name;
+        match roster.sync(deck) {
+            SyncOutcome::Added => {
+                let local = roster
+                    .decks()
+                    .find(|deck| deck.arena_id() == Some(arena_deck.id.as_str()))
+                    .map_or(name, |deck| &deck.name);
+                if local == name {
+                    println!("Added `{name}`");
+                } else {
+                    println!("Added `{name}` as `{local}`");
+                }
+            }
+            SyncOutcome::Linked => println!("Linked `{name}` to its Arena deck"),
+            SyncOutcome::Updated => println!("Updated `{name}`"),
+            SyncOutcome::Unchanged => continue,
+        }
+        changed += 1;
+    }
+    println!(
+        "{changed} of {} Arena decks changed the roster",
+        log.decks.len()
+    );
+    Ok(())
+}
+
+/// Adds the games of the game log to the history, attributed to roster decks
+fn record_games(
+    log_path: Option<PathBuf>,
+    translator: &mut MtgaIdTranslator,
+    roster: &Roster,
+    history: &mut MatchHistory,
+) -> Result<()> {
+    // Without a log, the games recorded so far are still worth reporting
+    let Some(log_path) = log_path.or_else(|| PlayerLog::default_path().filter(|p| p.exists()))
+    else {
+        return Ok(());
+    };
+    let log = read_player_log(&log_path)?;
+    let mut recorded = 0;
+    for game in &log.games {
+        if history.contains(&game.match_id, game.game_number) {
+            continue;
+        }
+        let deck = match CardGetter::card_list(translator, &game.deck_cards) {
+            Ok(main) => match_history::attribute(roster, game.deck_id.as_deref(), &main),
+            Err(err) => {
+                eprintln!("Warning: could not read the deck of a game: {err:#}");
+                None
+            }
+        };
+        history.add(GameRecord {
+            match_id: game.match_id.clone(),
+            game_number: game.game_number,
+            deck: deck.map(|deck| deck.name.clone()),
+            on_the_play: game.on_the_play,
+            opponent_colors: game.opponent_colors.clone(),
+            result: game.result,
+        });
+        recorded += 1;
+    }
+    if recorded > 0 {
+        println!("Recorded {recorded} new games\n");
+    }
+    history.write()
+}
+
+fn print_stats(deck_name: Option<&str>, history: &MatchHistory) {
+    if let Some(deck_name) = deck_name {
+        let games = history
+            .games()
+            .filter(|game| game.deck.as_deref() == Some(deck_name))
+            .collect_vec();
+        if games.is_empty() {
+            println!("No game recorded with `{deck_name}`");
+        } else {
+            println!("{}", DeckStats::new(games));
+        }
+        return;
+    }
+    let by_deck = history
+        .games()
+        .into_group_map_by(|game| game.deck.as_deref().unwrap_or("Unknown deck"));
+    for (deck, games) in by_deck
+        .into_iter()
+        .sorted_by_key(|(_, games)| std::cmp::Reverse(games.len()))
+    {
+        println!("{deck}\t {}", DeckStats::new(games).overall);
+    }
+}
+
+fn export_roster(
+    file: &Path,
+    deck_names: &[String],
+    tag: Option<&str>,
+    roster: &Roster,
+) -> Result<()> {
+    for deck_name in deck_names {
+        roster.find(deck_name)?;
+    }
+    let decks = roster
+        .decks()
+        .filter(|deck| deck_names.is_empty() || deck_names.contains(&deck.name))
+        .filter(|deck| tag.iter().all(|tag| deck.has_tag(tag)))
+        .cloned()
+        .collect_vec();
+    let exported = decks.len();
+    RosterBundle::new(decks)
+        .write(file)
+        .with_context(|| format!("Failed to write roster bundle to {}", file.display()))?;
+    println!("Exported {exported} decks to {}", file.display());
+    Ok(())
+}
+
+fn import_roster(
+    file: &Path,
+    on_conflict: OnConflict,
+    auto_fix: bool,
+    roster: &mut Roster,
+    inventory: &mut Inventory,
+) -> Result<()> {
+    let mut bundle = RosterBundle::from_file(file)?;
+    for deck in &mut bundle.decks {
+        resolve_names(deck, inventory, auto_fix)?;
+    }
+    let summary = roster.merge(bundle.decks, on_conflict);
+    println!("{summary}");
+    Ok(())
+}
+
 #[allow(clippy::too_many_lines)]
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
@@ -252,35 +980,57 @@ fn main() -> anyhow::Result<()> {
         .collection_path
         .unwrap_or_else(|| app_dir.join("collection.json"));
     let wildcards_path = app_dir.join("wildcards.json");
+    let config_path = app_dir.join("config.json");
+    let mut config = Config::open(&config_path)
+        .with_context(|| format!("Failed to open config with path {config_path:?}"))?;
     let mut translator = MtgaIdTranslator::load_from_file(app_dir.join("translator.ron"))
         .context("Failed to load translator.json file")?;
+    let calendar_path = app_dir.join("sets.json");
+    let calendar = SetCalendar::open(&calendar_path)?;
     let mut roster = Roster::open(&roster_path)
         .with_context(|| format!("Failed to open deck roster with path {roster_path:?}"))?;
-    let mut inventory = Inventory::open(&collection_path, &wildcards_path).with_context(|| {
+    let inventory = Inventory::open(&collection_path, &wildcards_path).with_context(|| {
         format!("Failed to open inventory with paths {collection_path:?}, and {wildcards_path:?}")
     })?;
+    let mut inventory = inventory.with_wish_cards(config.wish_cards.clone());
     let ignore_sideboard = cli.ignore_sb;
     match cli.command {
-        Some(Commands::AddFromFile { deck_paths, names }) => {
-            add_from_file(&deck_paths, names.as_ref(), &mut roster)?;
-        }
-        Some(Commands::Booster) => {
-            // For each set, sums up the card values
-            let mut set_values = HashMap::new();
-            for (card_name, amount) in roster.cards(ignore_sideboard) {
-                let card_cheapest_version = inventory.cheapest_version(card_name)?;
-                let set_name = &card_cheapest_version.2;
-                let card_cost = inventory.card_cost(card_name)?;
-                let missing_amount = amount.saturating_sub(inventory.card_amount(card_name)?);
-                *set_values.entry(set_name).or_insert(0.0) += card_cost * f32::from(missing_amount);
-            }
-            let mut set_values = set_values.iter().collect_vec();
-            set_values.sort_unstable_by(|(_, v1), (_, v2)| v2.partial_cmp(v1).unwrap());
-            set_values
-                .iter()
-                .take(10)
-                .for_each(|(set, value)| println!("{value} {set}"));
+        Some(Commands::AddFromFile {
+            deck_paths,
+            names,
+            auto_fix,
+        }) => {
+            add_from_file(
+                &deck_paths,
+                names.as_ref(),
+                &mut roster,
+                &mut inventory,
+                &mut translator,
+                auto_fix,
+            )?;
+        }
+        Some(Commands::Booster {
+            trials,
+            packs,
+            seed,
+            draft,
+            discount_rotating,
+        }) => {
+            warn_rotating(&config, &roster, &inventory, &calendar);
+            booster(
+                &config,
+                &roster,
+                &inventory,
+                ignore_sideboard,
+                trials,
+                packs,
+                seed,
+                draft,
+                discount_rotating.then_some(&calendar),
+            )?;
         }
+        Some(Commands::Sets) => list_sets(&calendar),
+        Some(Commands::Completion { set }) => completion(&inventory, set.as_deref()),
         Some(Commands::Edit { deck_name }) => {
             let deck = roster.find(&deck_name)?;
             let tmp_file = Temp::new_file()?;
@@ -298,15 +1048,96 @@ fn main() -> anyhow::Result<()> {
             let modified_deck = fs::read_to_string(tmp_file)
                 .context("When attempting to read temp file")?
                 .parse::<Deck>()?
-                .name(&deck_name);
+                .name(&deck_name)
+                .with_metadata_of(deck);
+            warn_if_invalid(&modified_deck, &mut translator);
             roster.replace(&deck_name, modified_deck)?;
         }
+        Some(Commands::Analyze { deck_name }) => {
+            let deck = roster.find(&deck_name)?;
+            let card_data = card_data_of(deck, &mut translator);
+            println!("{}", DeckAnalysis::new(deck, &card_data));
+        }
+        Some(Commands::Manabase { deck_name }) => {
+            let deck = roster.find(&deck_name)?;
+            let card_data = card_data_of(deck, &mut translator);
+            let mana_base = ManaBase::new(deck, &card_data);
+            println!("{mana_base}");
+            for color in mana_base.under_supported() {
+                eprintln!(
+                    "Warning: `{}` has too few {} sources",
+                    deck.name,
+                    color.symbol()
+                );
+            }
+        }
+        Some(Commands::Odds {
+            deck_name,
+            groups,
+            at_least,
+            turns,
+        }) => {
+            let deck = roster.find(&deck_name)?;
+            if groups.is_empty() {
+                let card_data = card_data_of(deck, &mut translator);
+                print_land_odds(deck, &DeckAnalysis::new(deck, &card_data));
+            } else {
+                print_draw_odds(deck, &groups, at_least, turns)?;
+            }
+        }
+        Some(Commands::Draw { deck_name, seed }) => draw(roster.find(&deck_name)?, seed),
+        Some(Commands::Simulate {
+            deck_name,
+            trials,
+            seed,
+            lands: LandRange(min_lands, max_lands),
+            keep_at,
+            turns,
+            draw,
+        }) => {
+            let rule = KeepRule {
+                min_lands,
+                max_lands,
+                keep_at,
+            };
+            let deck = roster.find(&deck_name)?;
+            simulate(deck, &mut translator, trials, seed, &rule, turns, !draw);
+        }
+        Some(Commands::Budget {
+            deck_name,
+            max_rares,
+            name,
+        }) => budget(
+            &deck_name,
+            name,
+            max_rares,
+            &config,
+            &mut roster,
+            &inventory,
+            &mut translator,
+        )?,
+        Some(Commands::Validate { deck_name }) => {
+            let decks = match deck_name {
+                Some(deck_name) => vec![roster.find(&deck_name)?],
+                None => roster.decks().collect(),
+            };
+            for deck in decks {
+                let problems = validate(deck, &companion_card_data(deck, &mut translator));
+                if problems.is_empty() {
+                    println!("`{}` is valid", deck.name);
+                }
+                for problem in problems {
+                    println!("`{}`: {problem}", deck.name);
+                }
+            }
+        }
         Some(Commands::Export { deck_name }) => export(&deck_name, &roster)?,
         Some(Commands::List) => {
+            println!("Deck cost: {}\n", config.deck_cost);
             let costs = roster
                 .decks()
                 .map(|deck| {
-                    (inventory.deck_cost(deck, ignore_sideboard))
+                    (inventory.deck_cost(deck, ignore_sideboard, &config.deck_cost))
                         .with_context(|| format!("Failed to compute deck cost for `{}`", deck.name))
                 })
                 .collect::<Result<Vec<_>>>()?; // Just collect here, to make error-handling less of a headache
@@ -315,35 +1146,63 @@ fn main() -> anyhow::Result<()> {
             for (coeff, deck) in decks {
                 println!("{coeff:.2}\t {}", deck.name);
             }
+            warn_rotating(&config, &roster, &inventory, &calendar);
         }
         Some(Commands::Missing { deck_name }) => {
             missing(&deck_name, &roster, &inventory, ignore_sideboard)?;
         }
-        Some(Commands::Paste { name }) => {
-            let deck: Deck =
-                clipboard_win::get_clipboard::<String, _>(clipboard_win::formats::Unicode)
-                    .map_err(|err| anyhow!("Failed to read clipboard: {err}"))?
-                    .parse::<Deck>()
-                    .context("Failed to parse deck from clipboard")?
-                    .name(&name);
+        Some(Commands::Paste { name, auto_fix }) => {
+            let text = clipboard_win::get_clipboard::<String, _>(clipboard_win::formats::Unicode)
+                .map_err(|err| anyhow!("Failed to read clipboard: {err}"))?;
+            let mut deck =
+                parse_deck(&text, &name).context("Failed to parse deck from clipboard")?;
+            resolve_names(&mut deck, &mut inventory, auto_fix)?;
+            warn_if_invalid(&deck, &mut translator);
             roster.add_deck(deck);
         }
+        Some(Commands::NeverCraft(action)) => {
+            match action {
+                NeverCraft::Add { cards } => {
+                    for card in cards {
+                        config
+                            .never_craft
+                            .insert(inventory.collection().canonical_name(&card)?);
+                    }
+                }
+                NeverCraft::Remove { cards } => {
+                    for card in cards {
+                        let card = inventory.collection().canonical_name(&card).unwrap_or(card);
+                        if !config.never_craft.remove(&card) {
+                            eprintln!("`{card}` was not in the never craft list");
+                        }
+                    }
+                }
+                NeverCraft::List => {
+                    for card in &config.never_craft {
+                        println!("{card}");
+                    }
+                    return Ok(());
+                }
+            }
+            config
+                .write(&config_path)
+                .with_context(|| format!("Failed to write config with path {config_path:?}"))?;
+        }
         Some(Commands::PrintCoeffs) => println!("{:?}", inventory.wildcard_coeffs()),
         Some(Commands::Recommend {
             rare_limit,
             mythic_limit,
             with,
         }) => {
-            let collection = Collection::open(&collection_path)?;
             let craft_suggester = CraftRecommender::new(
                 rare_limit,
                 mythic_limit,
                 ignore_sideboard,
                 with,
                 &roster,
-                &collection,
+                &inventory,
             );
-            let result = craft_suggester.recommend();
+            let result = craft_suggester.recommend()?;
             println!("{result:#?}");
         }
         Some(Commands::Remove { deck_name }) => {
@@ -369,25 +1228,134 @@ fn main() -> anyhow::Result<()> {
                 uncommon: uncommon as f32,
                 rare: rare as f32,
                 mythic: mythic as f32,
+                vault_progress: inventory.vault_progress(),
+            };
+            fs::write(wildcards_path, serde_json::to_string(&wildcards)?)?;
+        }
+        Some(Commands::SetVault { progress }) => {
+            let wildcards = Wildcards {
+                vault_progress: progress,
+                ..Wildcards::open(&wildcards_path)?
             };
             fs::write(wildcards_path, serde_json::to_string(&wildcards)?)?;
         }
         Some(Commands::Show { deck_name }) => {
-            roster.find(&deck_name).map(|deck| println!("{deck}"))?;
+            let deck = roster.find(&deck_name)?;
+            println!("{deck}");
+            let tags = deck.tags().join(", ");
+            if !tags.is_empty() {
+                println!("Tags: {tags}");
+            }
+            let rebalanced = deck.rebalanced_cards().join(", ");
+            if !rebalanced.is_empty() {
+                println!(
+                    "Plays rebalanced cards, only legal in Alchemy and Historic: {rebalanced}"
+                );
+            }
         }
-        Some(Commands::Suggest { equally }) => {
-            suggest(&roster, &mut inventory, ignore_sideboard, equally)?;
+        Some(Commands::Suggest {
+            equally,
+            explain,
+            expanded,
+            top,
+            page,
+            rarity,
+            set,
+            tag,
+        }) => {
+            let filter = SuggestionFilter {
+                rarities: &rarity,
+                set: set.as_deref(),
+                tag: tag.as_deref(),
+                never_craft: &config.never_craft,
+            };
+            suggest(
+                &roster,
+                &inventory,
+                ignore_sideboard,
+                equally,
+                &config.deck_cost,
+                filter,
+                explain.as_deref(),
+                expanded,
+                top,
+                page,
+            )?;
+        }
+        Some(Commands::Tag {
+            deck_name,
+            tags,
+            remove,
+        }) => {
+            let deck = roster.find_mut(&deck_name)?;
+            for tag in &tags {
+                if remove {
+                    deck.remove_tag(tag);
+                } else {
+                    deck.add_tag(tag);
+                }
+            }
+        }
+        Some(Commands::Roster(RosterCommand::Export { file, decks, tag })) => {
+            export_roster(&file, &decks, tag.as_deref(), &roster)?;
         }
-        Some(Commands::UpdateCollection) => {
+        Some(Commands::Roster(RosterCommand::Import {
+            file,
+            on_conflict,
+            auto_fix,
+        })) => import_roster(&file, on_conflict, auto_fix, &mut roster, &mut inventory)?,
+        Some(Commands::Serve { port }) => {
+            let server = tiny_http::Server::http(("127.0.0.1", port))
+                .map_err(|err| anyhow!("Failed to listen on port {port}: {err}"))?;
+            println!("Listening on http://127.0.0.1:{port}");
+            Api::new(&mut roster, &mut inventory, &config, ignore_sideboard).serve(&server);
+        }
+        Some(Commands::Tui) => {
+            let mut app = App::new(&mut roster, &inventory, &config, ignore_sideboard);
+            tui::run(&mut app)?;
+        }
+        Some(Commands::UpdateCollection { log, log_path }) => {
             // std::fs::copy(path, collection_path)?;
-            let recently_fetched =
-                CardGetter::owned_cards(&mut translator).context("Failed to get owned cards")?;
+            let recently_fetched = if log {
+                let log_path = log_path
+                    .or_else(PlayerLog::default_path)
+                    .context("Could not find the game log, pass its path to --log-path")?;
+                let log = read_player_log(&log_path)?;
+                if let Some(arena_inventory) = &log.inventory {
+                    let wildcards = &arena_inventory.wildcards;
+                    fs::write(&wildcards_path, serde_json::to_string(wildcards)?)?;
+                    println!(
+                        "Wildcards: {} commons, {} uncommons, {} rares, {} mythics. Vault: {:.1}%. Gold: {}, gems: {}",
+                        wildcards.common,
+                        wildcards.uncommon,
+                        wildcards.rare,
+                        wildcards.mythic,
+                        wildcards.vault_progress,
+                        arena_inventory.gold,
+                        arena_inventory.gems
+                    );
+                }
+                CardGetter::owned_cards_from_log(&mut translator, &log)?
+            } else {
+                CardGetter::owned_cards(&mut translator).context("Failed to get owned cards")?
+            };
             inventory.update_collection(recently_fetched, &roster);
         }
+        Some(Commands::SyncArenaDecks { log_path }) => {
+            sync_arena_decks(log_path, &mut translator, &mut roster, &mut inventory)?;
+        }
+        Some(Commands::Stats {
+            deck_name,
+            log_path,
+        }) => {
+            let mut history = MatchHistory::open(app_dir.join("matches.json"))?;
+            record_games(log_path, &mut translator, &roster, &mut history)?;
+            print_stats(deck_name.as_deref(), &history);
+        }
         Some(Commands::Which { query }) => {
             let re = Regex::new(&query)?;
             for deck in roster.decks() {
-                for (card_name, amount) in deck.cards(ignore_sideboard) {
+                for (card_name, amount) in deck.cards(inventory.sideboard(ignore_sideboard)) {
                     if re.is_match(&card_name.to_lowercase()) {
                         println!("{}\t{amount} {card_name}", deck.name);
                     }
@@ -396,10 +1364,10 @@ fn main() -> anyhow::Result<()> {
         }
         Some(Commands::WhichSet { set: set_name }) => {
             let mut found_cards = HashMap::new();
-            for (card_name, amount) in roster.cards(ignore_sideboard) {
+            for (card_name, amount) in roster.cards(inventory.sideboard(ignore_sideboard)) {
                 let card = inventory.cheapest_version(card_name)?;
                 if card.2 == set_name {
-                    let missing_amount = amount.saturating_sub(inventory.card_amount(card_name)?);
+                    let missing_amount = inventory.missing_amount(card_name, amount)?;
                     *found_cards.entry(card_name).or_insert(0) += missing_amount;
                 }
             }
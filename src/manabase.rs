@@ -0,0 +1,328 @@
+use crate::{
+    analysis::{pips, CardType},
+    mtga_id_translator::NetCardData,
+    Color, Deck, Zone,
+};
+use regex::Regex;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+    sync::OnceLock,
+};
+
+/// Size of the decks the source tables are for
+const TABLE_DECK_SIZE: u32 = 60;
+
+/// Colored sources a 60 card deck needs to cast a spell on curve 90% of the
+/// time, by colored pips of the spell and turn, from Frank Karsten's tables.
+/// Spells of mana value 7 and more share the last column.
+const SOURCES_NEEDED: [[u32; 7]; 4] = [
+    [14, 13, 12, 10, 10, 9, 8],
+    [0, 21, 18, 16, 15, 14, 13],
+    [0, 0, 23, 20, 19, 17, 16],
+    [0, 0, 0, 24, 22, 21, 19],
+];
+
+const BASIC_LAND_TYPES: [(&str, Color); 5] = [
+    ("Plains", Color::White),
+    ("Island", Color::Blue),
+    ("Swamp", Color::Black),
+    ("Mountain", Color::Red),
+    ("Forest", Color::Green),
+];
+
+/// Colors of the basic land types named in a text
+fn basic_land_colors(text: &str) -> impl Iterator<Item = Color> + '_ {
+    BASIC_LAND_TYPES
+        .into_iter()
+        .filter(|(land_type, _)| {
+            text.split(|c: char| !c.is_alphabetic())
+                .any(|w| w == *land_type)
+        })
+        .map(|(_, color)| color)
+}
+
+/// Colors a land can produce, from its land types and rules text. Lands that
+/// fetch a basic land count for the colors of the basics of the deck.
+#[must_use]
+#[allow(clippy::missing_panics_doc)]
+pub fn produced_colors(land: &NetCardData, basic_colors: &BTreeSet<Color>) -> BTreeSet<Color> {
+    static ADD: OnceLock<Regex> = OnceLock::new();
+    static SEARCH: OnceLock<Regex> = OnceLock::new();
+    let add = ADD.get_or_init(|| Regex::new(r"Add ([^.]*)").expect("Invalid regex"));
+    let search = SEARCH
+        .get_or_init(|| Regex::new(r"[Ss]earch your library for ([^.]*)").expect("Invalid regex"));
+
+    let mut colors: BTreeSet<Color> = basic_land_colors(&land.type_line).collect();
+    for mana in add.captures_iter(&land.oracle_text) {
+        if mana[1].contains("any color") {
+            colors.extend(Color::ALL);
+        }
+        for color in Color::ALL {
+            if mana[1].contains(&format!("{{{}}}", color.symbol())) {
+                colors.insert(color);
+            }
+        }
+    }
+    for fetched in search.captures_iter(&land.oracle_text) {
+        let mut fetched_colors = basic_land_colors(&fetched[1]).peekable();
+        if fetched_colors.peek().is_none() && fetched[1].contains("basic land") {
+            colors.extend(basic_colors);
+        }
+        colors.extend(fetched_colors);
+    }
+    colors
+}
+
+/// Sources of a color needed to cast a spell on curve, for a main deck of
+/// `library` cards, or `None` if the spell has no pips of that color
+#[must_use]
+pub fn sources_needed(pips: u32, mana_value: u32, library: u32) -> Option<u32> {
+    if pips == 0 {
+        return None;
+    }
+    let row = SOURCES_NEEDED[pips.min(4) as usize - 1];
+    let turn = mana_value.clamp(pips.min(4), 7);
+    let needed = row[turn as usize - 1];
+    Some((needed * library).div_ceil(TABLE_DECK_SIZE))
+}
+
+/// The spell that needs the most sources of a color
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement {
+    pub card: String,
+    pub mana_cost: String,
+    pub needed: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColorSources {
+    pub sources: u32,
+    pub requirement: Option<Requirement>,
+}
+
+impl ColorSources {
+    #[must_use]
+    pub fn under_supported(&self) -> bool {
+        self.requirement
+            .as_ref()
+            .is_some_and(|requirement| self.sources < requirement.needed)
+    }
+}
+
+/// Colored sources of the lands of a deck, against the needs of its spells
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManaBase {
+    pub lands: u32,
+    pub colors: BTreeMap<Color, ColorSources>,
+    /// Cards without card data
+    pub unknown: u32,
+}
+
+impl ManaBase {
+    #[must_use]
+    pub fn new(deck: &Deck, card_data: &HashMap<String, NetCardData>) -> Self {
+        let mut mana_base = Self::default();
+        let cards = deck
+            .cards_by_zone()
+            .filter(|(zone, _, _)| *zone == Zone::Main)
+            .filter_map(|(_, name, amount)| {
+                let data = card_data.get(name);
+                if data.is_none() {
+                    mana_base.unknown += u32::from(amount);
+                }
+                Some((data?, u32::from(amount)))
+            })
+            .collect::<Vec<_>>();
+        let library: u32 = cards.iter().map(|(_, amount)| amount).sum::<u32>() + mana_base.unknown;
+        let (lands, spells): (Vec<_>, Vec<_>) = cards
+            .into_iter()
+            .partition(|(data, _)| CardType::of(&data.type_line) == CardType::Land);
+
+        let basic_colors = lands
+            .iter()
+            .filter(|(data, _)| data.type_line.contains("Basic"))
+            .flat_map(|(data, _)| basic_land_colors(&data.type_line))
+            .collect();
+        for (land, amount) in &lands {
+            mana_base.lands += amount;
+            for color in produced_colors(land, &basic_colors) {
+                mana_base.colors.entry(color).or_default().sources += amount;
+            }
+        }
+        for (spell, _) in spells {
+            // Mana values are whole numbers, but for a few Un-cards
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let mana_value = spell.cmc as u32;
+            for (color, pips) in pips(&spell.mana_cost) {
+                let Some(needed) = sources_needed(pips, mana_value, library) else {
+                    continue;
+                };
+                let color_sources = mana_base.colors.entry(color).or_default();
+                if color_sources
+                    .requirement
+                    .as_ref()
+                    .is_some_and(|requirement| requirement.needed >= needed)
+                {
+                    continue;
+                }
+                color_sources.requirement = Some(Requirement {
+                    card: spell.name.clone(),
+                    mana_cost: spell.mana_cost.clone(),
+                    needed,
+                });
+            }
+        }
+        mana_base
+    }
+
+    pub fn under_supported(&self) -> impl Iterator<Item = Color> + '_ {
+        self.colors
+            .iter()
+            .filter(|(_, sources)| sources.under_supported())
+            .map(|(color, _)| *color)
+    }
+}
+
+impl fmt::Display for ManaBase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} lands\n\nColor\t Sources\t Needed", self.lands)?;
+        for (color, sources) in &self.colors {
+            write!(f, "\n{}\t {}", color.symbol(), sources.sources)?;
+            if let Some(requirement) = &sources.requirement {
+                write!(
+                    f,
+                    "\t\t {}\t for {} {}",
+                    requirement.needed, requirement.card, requirement.mana_cost
+                )?;
+                if sources.under_supported() {
+                    write!(f, "\t under-supported")?;
+                }
+            }
+        }
+        if self.unknown > 0 {
+            write!(f, "\n{} cards have no card data", self.unknown)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rarity;
+
+    fn card(name: &str, mana_cost: &str, type_line: &str, oracle_text: &str) -> NetCardData {
+        NetCardData {
+            name: name.to_owned(),
+            rarity: Rarity::Common,
+            set: String::new(),
+            mana_cost: mana_cost.to_owned(),
+            // Each symbol is worth one mana in the costs used here
+            cmc: f32::from(u8::try_from(mana_cost.matches('{').count()).unwrap()),
+            colors: Vec::new(),
+            color_identity: Vec::new(),
+            type_line: type_line.to_owned(),
+            oracle_text: oracle_text.to_owned(),
+            arena_id: None,
+        }
+    }
+
+    #[test]
+    fn sources() {
+        let basics = BTreeSet::from([Color::White, Color::Red]);
+        let colors = |land: &NetCardData| produced_colors(land, &basics);
+        assert_eq!(
+            colors(&card(
+                "Plains",
+                "",
+                "Basic Land — Plains",
+                "({T}: Add {W}.)"
+            )),
+            BTreeSet::from([Color::White])
+        );
+        let pathway = card(
+            "Needleverge Pathway // Pillarverge Pathway",
+            "",
+            "Land // Land",
+            "{T}: Add {R}.\n//\n{T}: Add {W}.",
+        );
+        assert_eq!(colors(&pathway), basics);
+        let fetch = card(
+            "Wooded Foothills",
+            "",
+            "Land",
+            "{T}, Pay 1 life, Sacrifice Wooded Foothills: Search your library for a Mountain or Forest card, put it onto the battlefield, then shuffle.",
+        );
+        assert_eq!(colors(&fetch), BTreeSet::from([Color::Red, Color::Green]));
+        let passage = card(
+            "Fabled Passage",
+            "",
+            "Land",
+            "{T}, Sacrifice Fabled Passage: Search your library for a basic land card, put it onto the battlefield tapped, then shuffle.",
+        );
+        assert_eq!(colors(&passage), basics);
+        let any = card(
+            "Plaza of Heroes",
+            "",
+            "Land",
+            "{T}: Add {C}.\n{T}: Add one mana of any color.",
+        );
+        assert_eq!(colors(&any).len(), 5);
+
+        assert_eq!(sources_needed(1, 1, 60), Some(14));
+        assert_eq!(sources_needed(2, 2, 60), Some(21));
+        assert_eq!(sources_needed(1, 1, 40), Some(10));
+        assert_eq!(sources_needed(0, 3, 60), None);
+    }
+
+    #[test]
+    fn under_supported() {
+        let card_data = HashMap::from(
+            [
+                card("Mountain", "", "Basic Land — Mountain", ""),
+                card("Plains", "", "Basic Land — Plains", ""),
+                card("Shock", "{R}", "Instant", ""),
+                NetCardData {
+                    cmc: 3.0,
+                    ..card(
+                        "Bonecrusher Giant",
+                        "{2}{R} // {1}{R}",
+                        "Creature — Giant // Instant — Adventure",
+                        "",
+                    )
+                },
+                card("Glorybringer", "{3}{R}{R}", "Creature — Dragon", ""),
+                card(
+                    "Adeline, Resplendent Cathar",
+                    "{1}{W}{W}",
+                    "Legendary Creature — Human Knight",
+                    "",
+                ),
+            ]
+            .map(|data| (data.name.clone(), data)),
+        );
+        let deck: Deck =
+            "Deck\n16 Mountain\n8 Plains\n4 Shock\n4 Adeline, Resplendent Cathar\n4 Bonecrusher Giant\n24 Island\n\nSideboard\n4 Glorybringer\n"
+                .parse()
+                .unwrap();
+        let mana_base = ManaBase::new(&deck, &card_data);
+        assert_eq!(mana_base.lands, 24);
+        assert_eq!(mana_base.unknown, 24);
+        assert_eq!(mana_base.colors[&Color::White].sources, 8);
+        // Only the front face of Bonecrusher Giant counts, and the
+        // sideboard Glorybringer not at all
+        assert_eq!(
+            mana_base.colors[&Color::Red]
+                .requirement
+                .as_ref()
+                .unwrap()
+                .card,
+            "Shock"
+        );
+        assert_eq!(
+            mana_base.under_supported().collect::<Vec<_>>(),
+            vec![Color::White]
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use crate::{Deck, Roster, Zone};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GameResult {
+    Win,
+    Loss,
+    Draw,
+}
+
+/// A game played in Arena, attributed to a deck of the roster
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub match_id: String,
+    pub game_number: u8,
+    /// Name of the roster deck the game was played with, when one matched
+    pub deck: Option<String>,
+    pub on_the_play: Option<bool>,
+    /// Colors of the opponent's cards seen during the game, in WUBRG order
+    pub opponent_colors: String,
+    pub result: GameResult,
+}
+
+/// The roster deck a game was played with: the deck linked to the Arena deck,
+/// or else the one sharing the most cards with the main deck, if it shares at
+/// least half of it
+#[must_use]
+pub fn attribute<'r>(
+    roster: &'r Roster,
+    arena_id: Option<&str>,
+    main: &[(u8, String)],
+) -> Option<&'r Deck> {
+    if let Some(deck) = roster
+        .decks()
+        .find(|deck| deck.arena_id().is_some() && deck.arena_id() == arena_id)
+    {
+        return Some(deck);
+    }
+    let list: HashMap<&str, u32> = main
+        .iter()
+        .map(|(amount, name)| (name.as_str(), u32::from(*amount)))
+        .collect();
+    let size: u32 = list.values().sum();
+    roster
+        .decks()
+        .map(|deck| {
+            // Arena only lists the main deck of the games
+            let shared: u32 = deck
+                .cards_by_zone()
+                .filter(|(zone, _, _)| *zone == Zone::Main)
+                .map(|(_, name, amount)| {
+                    list.get(name.as_str())
+                        .map_or(0, |&listed| listed.min(u32::from(amount)))
+                })
+                .sum();
+            (deck, shared)
+        })
+        .filter(|&(_, shared)| shared > 0 && shared * 2 >= size)
+        .max_by_key(|&(_, shared)| shared)
+        .map(|(deck, _)| deck)
+}
+
+/// Games recorded so far, kept apart from the roster so that they outlive the
+/// game log, which the game overwrites on every launch
+#[derive(Debug)]
+pub struct MatchHistory {
+    path: PathBuf,
+    games: Vec<GameRecord>,
+}
+
+impl MatchHistory {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let games = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(path)?)
+                .with_context(|| format!("Failed to parse match history {}", path.display()))?
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            games,
+        })
+    }
+
+    pub fn write(&self) -> Result<()> {
+        fs::write(&self.path, serde_json::to_string(&self.games)?)?;
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn contains(&self, match_id: &str, game_number: u8) -> bool {
+        self.games
+            .iter()
+            .any(|game| game.match_id == match_id && game.game_number == game_number)
+    }
+
+    /// Records a game, unless it already is. Returns whether it was new.
+    pub fn add(&mut self, game: GameRecord) -> bool {
+        if self.contains(&game.match_id, game.game_number) {
+            return false;
+        }
+        self.games.push(game);
+        true
+    }
+
+    pub fn games(&self) -> impl Iterator<Item = &GameRecord> {
+        self.games.iter()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Record {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl Record {
+    fn add(&mut self, result: GameResult) {
+        match result {
+            GameResult::Win => self.wins += 1,
+            GameResult::Loss => self.losses += 1,
+            GameResult::Draw => self.draws += 1,
+        }
+    }
+
+    #[must_use]
+    pub const fn games(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+
+    /// Percent of games won
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn win_rate(&self) -> Option<f32> {
+        (self.games() > 0).then(|| self.wins as f32 / self.games() as f32 * 100.0)
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.wins, self.losses)?;
+        if self.draws > 0 {
+            write!(f, "-{}", self.draws)?;
+        }
+        match self.win_rate() {
+            Some(win_rate) => write!(f, " ({win_rate:.1}%)"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeckStats {
+    pub overall: Record,
+    pub on_the_play: Record,
+    pub on_the_draw: Record,
+    /// By colors of the opponent, in WUBRG order
+    pub against: BTreeMap<String, Record>,
+}
+
+impl DeckStats {
+    pub fn new<'g>(games: impl IntoIterator<Item = &'g GameRecord>) -> Self {
+        let mut stats = Self::default();
+        for game in games {
+            stats.overall.add(game.result);
+            match game.on_the_play {
+                Some(true) => stats.on_the_play.add(game.result),
+                Some(false) => stats.on_the_draw.add(game.result),
+                None => {}
+            }
+            stats
+                .against
+                .entry(game.opponent_colors.clone())
+                .or_default()
+                .add(game.result);
+        }
+        stats
+    }
+}
+
+impl fmt::Display for DeckStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Overall\t\t {}", self.overall)?;
+        writeln!(f, "On the play\t {}", self.on_the_play)?;
+        writeln!(f, "On the draw\t {}", self.on_the_draw)?;
+        write!(f, "Against")?;
+        let mut against = self.against.iter().collect::<Vec<_>>();
+        against.sort_by_key(|(_, record)| std::cmp::Reverse(record.games()));
+        for (colors, record) in against {
+            let colors = if colors.is_empty() { "?" } else { colors };
+            write!(f, "\n  {colors}\t\t {record}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(on_the_play: bool, opponent_colors: &str, result: GameResult) -> GameRecord {
+        GameRecord {
+            match_id: String::new(),
+            game_number: 1,
+            deck: None,
+            on_the_play: Some(on_the_play),
+            opponent_colors: opponent_colors.to_owned(),
+            result,
+        }
+    }
+
+    #[test]
+    fn stats() {
+        let games = [
+            game(true, "R", GameResult::Win),
+            game(false, "R", GameResult::Loss),
+            game(true, "UB", GameResult::Win),
+            game(false, "R", GameResult::Draw),
+        ];
+        let stats = DeckStats::new(&games);
+        assert_eq!(
+            stats.overall,
+            Record {
+                wins: 2,
+                losses: 1,
+                draws: 1
+            }
+        );
+        assert_eq!(stats.on_the_play.win_rate(), Some(100.0));
+        assert_eq!(stats.on_the_draw.wins, 0);
+        assert_eq!(stats.against["R"].games(), 3);
+        assert_eq!(stats.overall.to_string(), "2-1-1 (50.0%)");
+    }
+}
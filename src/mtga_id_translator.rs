@@ -7,15 +7,111 @@ use std::fs::File;
 use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
-use crate::Rarity;
+use crate::{collection::simplified_name, Color, Rarity};
 
 const SCRYFALL_WAIT_TIME: Duration = Duration::from_millis(75);
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// What we keep of a card from scryfall. Caches written before the card
+/// details were kept only have the name, rarity and set.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct NetCardData {
     pub name: String,
     pub rarity: Rarity,
     pub set: String,
+    /// Like `{1}{R}{R}`, empty for lands
+    #[serde(default)]
+    pub mana_cost: String,
+    /// Mana value
+    #[serde(default)]
+    pub cmc: f32,
+    #[serde(default)]
+    pub colors: Vec<Color>,
+    #[serde(default)]
+    pub color_identity: Vec<Color>,
+    #[serde(default)]
+    pub type_line: String,
+    #[serde(default)]
+    pub oracle_text: String,
+    #[serde(default)]
+    pub arena_id: Option<u32>,
+}
+
+impl NetCardData {
+    /// Whether the card holds more than the name, rarity and set. Every card
+    /// has a type line.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        !self.type_line.is_empty()
+    }
+}
+
+#[derive(Deserialize)]
+struct ScryfallFace {
+    #[serde(default)]
+    mana_cost: String,
+    colors: Option<Vec<Color>>,
+    #[serde(default)]
+    oracle_text: String,
+}
+
+/// A card as scryfall describes it. Double-faced cards keep their mana cost,
+/// colors and rules text on their faces.
+#[derive(Deserialize)]
+pub(crate) struct ScryfallCard {
+    name: String,
+    rarity: Rarity,
+    set: String,
+    mana_cost: Option<String>,
+    #[serde(default)]
+    cmc: f32,
+    colors: Option<Vec<Color>>,
+    #[serde(default)]
+    color_identity: Vec<Color>,
+    #[serde(default)]
+    type_line: String,
+    oracle_text: Option<String>,
+    arena_id: Option<u32>,
+    #[serde(default)]
+    card_faces: Vec<ScryfallFace>,
+}
+
+impl From<ScryfallCard> for NetCardData {
+    fn from(card: ScryfallCard) -> Self {
+        let faces = &card.card_faces;
+        let mana_cost = card
+            .mana_cost
+            .filter(|cost| !cost.is_empty())
+            .or_else(|| faces.first().map(|face| face.mana_cost.clone()))
+            .unwrap_or_default();
+        let colors = card.colors.unwrap_or_else(|| {
+            let mut colors = faces
+                .iter()
+                .flat_map(|face| face.colors.iter().flatten().copied())
+                .collect::<Vec<_>>();
+            colors.sort_unstable();
+            colors.dedup();
+            colors
+        });
+        let oracle_text = card.oracle_text.unwrap_or_else(|| {
+            faces
+                .iter()
+                .map(|face| face.oracle_text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n//\n")
+        });
+        Self {
+            name: card.name,
+            rarity: card.rarity,
+            set: card.set,
+            mana_cost,
+            cmc: card.cmc,
+            colors,
+            color_identity: card.color_identity,
+            type_line: card.type_line,
+            oracle_text,
+            arena_id: card.arena_id,
+        }
+    }
 }
 
 pub struct MtgaIdTranslator {
@@ -47,10 +143,24 @@ impl MtgaIdTranslator {
         self.last_request_time.set(Some(Instant::now()));
     }
 
+    /// Entries of older caches are fetched again to complete them, unless
+    /// scryfall cannot be reached
     pub fn translate(&mut self, id: u32) -> Result<Option<NetCardData>> {
-        if let Some(card_data) = self.cache.get(&id) {
-            return Ok(card_data.clone());
+        let cached = self.cache.get(&id).cloned();
+        match cached {
+            Some(Some(card_data)) if !card_data.is_complete() => {
+                let fetched = self.fetch(id).unwrap_or_else(|_| {
+                    self.cache.insert(id, Some(card_data.clone()));
+                    card_data
+                });
+                Ok(Some(fetched))
+            }
+            Some(card_data) => Ok(card_data),
+            None => self.fetch(id).map(Some),
         }
+    }
+
+    fn fetch(&mut self, id: u32) -> Result<NetCardData> {
         self.handle_wait();
         let response =
             reqwest::blocking::get(format!("https://api.scryfall.com/cards/arena/{id}"))?;
@@ -64,10 +174,36 @@ impl MtgaIdTranslator {
             );
         }
         let card_data: NetCardData = response
-            .json()
-            .with_context(|| anyhow!("Failed to parse card with arena id {id}"))?;
+            .json::<ScryfallCard>()
+            .with_context(|| anyhow!("Failed to parse card with arena id {id}"))?
+            .into();
         self.cache.insert(id, Some(card_data.clone()));
-        Ok(Some(card_data))
+        Ok(card_data)
+    }
+
+    /// A cached card with this name, in any set
+    #[must_use]
+    pub fn find(&self, name: &str) -> Option<&NetCardData> {
+        let name = simplified_name(&name);
+        self.cache
+            .values()
+            .flatten()
+            .find(|card_data| simplified_name(&card_data.name) == name)
+    }
+
+    /// Every cached card with complete card data
+    pub fn cards(&self) -> impl Iterator<Item = &NetCardData> {
+        self.cache
+            .values()
+            .flatten()
+            .filter(|card_data| card_data.is_complete())
+    }
+
+    /// Caches a card fetched by name, if it is on Arena
+    pub fn remember(&mut self, card_data: NetCardData) {
+        if let Some(id) = card_data.arena_id {
+            self.cache.insert(id, Some(card_data));
+        }
     }
 
     fn write_out(&self) -> Result<()> {
@@ -92,15 +228,43 @@ mod tests {
     fn translate() -> Result<()> {
         let dictionary = NamedTempFile::new("dictionary.json")?;
         let mut translator = MtgaIdTranslator::load_from_file(dictionary)?;
-        let reply = translator.translate(75310)?;
-        assert_eq!(
-            reply,
-            Some(NetCardData {
-                name: "Hengegate Pathway // Mistgate Pathway".to_owned(),
-                rarity: Rarity::Rare,
-                set: "khm".to_owned(),
-            })
-        );
+        let reply = translator.translate(75310)?.unwrap();
+        assert_eq!(reply.name, "Hengegate Pathway // Mistgate Pathway");
+        assert_eq!(reply.rarity, Rarity::Rare);
+        assert_eq!(reply.set, "khm");
+        assert_eq!(reply.type_line, "Land // Land");
+        assert_eq!(reply.color_identity, vec![Color::White, Color::Blue]);
+        assert_eq!(reply.arena_id, Some(75310));
+        Ok(())
+    }
+
+    #[test]
+    fn double_faced_cards_keep_details_on_their_faces() -> Result<()> {
+        let card: ScryfallCard = serde_json::from_str(
+            r#"{"name": "Fable of the Mirror-Breaker // Reflection of Kiki-Jiki", "rarity": "rare",
+            "set": "neo", "cmc": 3.0, "color_identity": ["R"], "arena_id": 79519,
+            "type_line": "Enchantment — Saga // Enchantment Creature — Goblin Shaman",
+            "card_faces": [
+                {"mana_cost": "{2}{R}", "colors": ["R"], "oracle_text": "(As this Saga enters...)"},
+                {"mana_cost": "", "colors": ["R"], "oracle_text": "{1}, {T}: Create a token..."}
+            ]}"#,
+        )?;
+        let card = NetCardData::from(card);
+        assert_eq!(card.mana_cost, "{2}{R}");
+        assert_eq!(card.colors, vec![Color::Red]);
+        assert!(card.oracle_text.contains("\n//\n"));
+        assert!(card.is_complete());
+        Ok(())
+    }
+
+    #[test]
+    fn old_caches_still_load() -> Result<()> {
+        let cache: HashMap<u32, Option<NetCardData>> = ron::from_str(
+            r#"{67330: Some((name: "Shock", rarity: common, set: "m21")), 1: None}"#,
+        )?;
+        let shock = cache[&67330].as_ref().unwrap();
+        assert_eq!(shock.name, "Shock");
+        assert!(!shock.is_complete());
         Ok(())
     }
 }
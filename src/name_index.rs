@@ -0,0 +1,118 @@
+use itertools::Itertools;
+use std::collections::HashMap;
+
+const SIMILARITY_THRESHOLD: f64 = 0.75;
+
+/// Lowercases a card name and drops its punctuation, so that `Alchemists
+/// gambit` and `Alchemist's Gambit` are considered the same name.
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace() || *c == '-')
+        .flat_map(char::to_lowercase)
+        .collect::<String>()
+        .split_whitespace()
+        .join(" ")
+}
+
+/// Resolves card names that are not spelled exactly as we know them
+#[derive(Debug, Default)]
+pub struct NameIndex {
+    /// Normalized names and card faces, mapped to the name as we know it
+    entries: HashMap<String, String>,
+}
+
+impl NameIndex {
+    pub fn new<'a>(names: impl IntoIterator<Item = &'a String>) -> Self {
+        let names = names.into_iter().collect_vec();
+        let mut entries = HashMap::new();
+        for name in &names {
+            entries
+                .entry(normalize(name))
+                .or_insert_with(|| (*name).clone());
+        }
+        // Faces of split and double-faced cards come last, so that they never
+        // shadow a name we know exactly
+        for name in &names {
+            for face in name.split(" // ").skip(1) {
+                entries
+                    .entry(normalize(face))
+                    .or_insert_with(|| (*name).clone());
+            }
+        }
+        Self { entries }
+    }
+
+    /// Finds the name we know of, ignoring case and punctuation
+    #[must_use]
+    pub fn exact(&self, query: &str) -> Option<&str> {
+        self.entries.get(&normalize(query)).map(String::as_str)
+    }
+
+    /// The names closest to `query`, best match first
+    #[must_use]
+    pub fn suggestions(&self, query: &str, limit: usize) -> Vec<&str> {
+        let query = normalize(query);
+        self.entries
+            .iter()
+            .map(|(normalized, name)| {
+                let similarity = strsim::normalized_damerau_levenshtein(&query, normalized)
+                    .max(strsim::jaro_winkler(&query, normalized));
+                (similarity, name.as_str())
+            })
+            .filter(|(similarity, _)| *similarity >= SIMILARITY_THRESHOLD)
+            .sorted_by(|(s1, n1), (s2, n2)| s2.total_cmp(s1).then(n1.cmp(n2)))
+            .map(|(_, name)| name)
+            .unique()
+            .take(limit)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> NameIndex {
+        let names = [
+            "Alchemist's Gambit",
+            "Needleverge Pathway // Pillarverge Pathway",
+            "Needleverge Pathway",
+            "Fae of Wishes // Granted",
+            "Reprieve",
+        ]
+        .map(str::to_owned);
+        NameIndex::new(names.iter())
+    }
+
+    #[test]
+    fn ignores_case_and_punctuation() {
+        let index = index();
+        assert_eq!(index.exact("alchemists gambit"), Some("Alchemist's Gambit"));
+        assert_eq!(index.exact("REPRIEVE"), Some("Reprieve"));
+    }
+
+    #[test]
+    fn knows_every_face() {
+        let index = index();
+        assert_eq!(
+            index.exact("Needleverge Pathway"),
+            Some("Needleverge Pathway")
+        );
+        assert_eq!(
+            index.exact("Pillarverge Pathway"),
+            Some("Needleverge Pathway // Pillarverge Pathway")
+        );
+        assert_eq!(index.exact("granted"), Some("Fae of Wishes // Granted"));
+    }
+
+    #[test]
+    fn suggests_close_names() {
+        let index = index();
+        assert_eq!(index.suggestions("Reprieeve", 3), vec!["Reprieve"]);
+        assert_eq!(
+            index.suggestions("Alchemist Gambitt", 1),
+            vec!["Alchemist's Gambit"]
+        );
+        assert!(index.suggestions("Wrath of God", 3).is_empty());
+    }
+}
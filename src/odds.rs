@@ -0,0 +1,162 @@
+use crate::{Deck, Zone};
+use anyhow::{bail, Result};
+use itertools::Itertools;
+use std::{fmt, str::FromStr};
+
+pub const OPENING_HAND: u32 = 7;
+
+/// Ways to choose `k` items among `n`, as a float since they overflow
+/// integers for libraries of 60 cards
+#[must_use]
+pub fn binomial(n: u32, k: u32) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+    let k = k.min(n - k);
+    (0..k).fold(1.0, |ways, i| ways * f64::from(n - i) / f64::from(i + 1))
+}
+
+/// Probability to draw exactly `hits` of the `successes` cards of a library
+#[must_use]
+pub fn exactly(population: u32, successes: u32, draws: u32, hits: u32) -> f64 {
+    if hits > draws || successes > population {
+        return 0.0;
+    }
+    binomial(successes, hits) * binomial(population - successes, draws - hits)
+        / binomial(population, draws)
+}
+
+/// Probability to draw at least `hits` of the `successes` cards of a library
+#[must_use]
+pub fn at_least(population: u32, successes: u32, draws: u32, hits: u32) -> f64 {
+    all_of(population, &[(successes, hits)], draws)
+}
+
+/// Probability to draw at least the given hits of every group, given as its
+/// size and the hits it needs. Groups must not share cards.
+#[must_use]
+pub fn all_of(population: u32, groups: &[(u32, u32)], draws: u32) -> f64 {
+    fn ways(groups: &[(u32, u32)], rest: u32, draws: u32) -> f64 {
+        match groups.split_first() {
+            None => binomial(rest, draws),
+            Some((&(successes, hits), others)) => (hits..=successes.min(draws))
+                .map(|drawn| binomial(successes, drawn) * ways(others, rest, draws - drawn))
+                .sum(),
+        }
+    }
+    let in_groups: u32 = groups.iter().map(|(successes, _)| successes).sum();
+    if in_groups > population || draws > population {
+        return 0.0;
+    }
+    ways(groups, population - in_groups, draws) / binomial(population, draws)
+}
+
+/// Cards seen by the draw step of a turn, counting from 1
+#[must_use]
+pub fn cards_seen(turn: u32, on_the_play: bool) -> u32 {
+    OPENING_HAND + turn.saturating_sub(u32::from(on_the_play))
+}
+
+/// Cards that count as a hit, like `Shock|Lightning Strike`. Names are
+/// separated with `|`, as they may contain commas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardGroup {
+    pub cards: Vec<String>,
+}
+
+impl FromStr for CardGroup {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let cards = s
+            .split('|')
+            .map(str::trim)
+            .filter(|card| !card.is_empty())
+            .map(str::to_owned)
+            .collect_vec();
+        if cards.is_empty() {
+            bail!("Expected card names separated with `|`, found `{s}`");
+        }
+        Ok(Self { cards })
+    }
+}
+
+impl fmt::Display for CardGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.cards.join(", "))
+    }
+}
+
+/// Cards of the main deck, the library games start from
+#[must_use]
+pub fn library_size(deck: &Deck) -> u32 {
+    deck.cards_by_zone()
+        .filter(|(zone, _, _)| *zone == Zone::Main)
+        .map(|(_, _, amount)| u32::from(amount))
+        .sum()
+}
+
+/// Copies of the cards of each group in the main deck
+pub fn group_sizes(deck: &Deck, groups: &[CardGroup]) -> Result<Vec<u32>> {
+    let main = deck
+        .cards_by_zone()
+        .filter(|(zone, _, _)| *zone == Zone::Main)
+        .map(|(_, name, amount)| (name.to_lowercase(), u32::from(amount)))
+        .into_grouping_map()
+        .sum();
+    let mut seen = Vec::new();
+    groups
+        .iter()
+        .map(|group| {
+            group
+                .cards
+                .iter()
+                .map(|card| {
+                    let card = card.to_lowercase();
+                    if seen.contains(&card) {
+                        bail!("`{card}` is in several groups, groups must not share cards");
+                    }
+                    let Some(amount) = main.get(&card) else {
+                        bail!("`{card}` is not in the main deck of `{}`", deck.name);
+                    };
+                    seen.push(card);
+                    Ok(*amount)
+                })
+                .sum()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn hypergeometric() {
+        // 4 copies in 60 cards, seen in the opening hand
+        assert!(close(at_least(60, 4, 7, 1), 0.3995));
+        assert!(close(exactly(60, 24, 7, 3), 0.3087));
+        let lands = (2..=4).map(|hits| exactly(60, 24, 7, hits)).sum::<f64>();
+        assert!(close(lands, 0.7746));
+        assert!(close(all_of(60, &[(4, 1)], 7), at_least(60, 4, 7, 1)));
+        // Independent draws would give 0.3995², but groups compete for slots
+        assert!(close(all_of(60, &[(4, 1), (4, 1)], 7), 0.1454));
+        assert_eq!(cards_seen(1, true), 7);
+        assert_eq!(cards_seen(1, false), 8);
+    }
+
+    #[test]
+    fn groups() -> Result<()> {
+        let deck: Deck =
+            "Deck\n20 Mountain\n4 Shock\n4 Lightning Strike\n\nSideboard\n2 Abrade\n".parse()?;
+        let groups = ["shock | Lightning Strike".parse()?, "Mountain".parse()?];
+        assert_eq!(group_sizes(&deck, &groups)?, vec![8, 20]);
+        assert!(group_sizes(&deck, &["Abrade".parse()?]).is_err());
+        assert!(group_sizes(&deck, &["Shock".parse()?, "Shock".parse()?]).is_err());
+        assert_eq!(library_size(&deck), 28);
+        Ok(())
+    }
+}
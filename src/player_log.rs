@@ -0,0 +1,483 @@
+use crate::{match_history::GameResult, Wildcards};
+use anyhow::{Context, Result};
+use directories::BaseDirs;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Requests whose reply the log holds on the line after them, or at the end of
+/// the same line
+const CARDS_MARKER: &str = "GetPlayerCardsV3";
+const LEGACY_INVENTORY_MARKER: &str = "GetPlayerInventory";
+const LEGACY_DECK_LISTS_MARKER: &str = "GetDeckListsV3";
+
+/// `InventoryInfo` of the `StartHook` reply
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InventoryInfo {
+    gems: u32,
+    gold: u32,
+    wild_card_commons: u32,
+    #[serde(rename = "WildCardUnCommons")]
+    wild_card_uncommons: u32,
+    wild_card_rares: u32,
+    wild_card_mythics: u32,
+    /// In tenths of a percent
+    #[serde(default)]
+    total_vault_progress: f32,
+}
+
+/// Reply to `GetPlayerInventory`, in logs of older versions of the game
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LegacyInventory {
+    gems: u32,
+    gold: u32,
+    wc_common: u32,
+    wc_uncommon: u32,
+    wc_rare: u32,
+    wc_mythic: u32,
+    /// In percent
+    #[serde(default)]
+    vault_progress: f32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DeckSummary {
+    deck_id: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CardQuantity {
+    card_id: u32,
+    quantity: u8,
+}
+
+/// Cards of a deck of the `StartHook` reply, its name is in the summaries
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DeckCards {
+    #[serde(default)]
+    main_deck: Vec<CardQuantity>,
+    #[serde(default)]
+    sideboard: Vec<CardQuantity>,
+    #[serde(default)]
+    companions: Vec<CardQuantity>,
+}
+
+/// Deck of the reply to `GetDeckListsV3`, in logs of older versions of the
+/// game. Cards are given as ids followed by amounts.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LegacyDeck {
+    id: String,
+    name: String,
+    #[serde(default)]
+    main_deck: Vec<u32>,
+    #[serde(default)]
+    sideboard: Vec<u32>,
+    #[serde(default, rename = "companionGRPId")]
+    companion: u32,
+}
+
+fn id_amount_pairs(flat: &[u32]) -> Vec<(u32, u8)> {
+    flat.chunks_exact(2)
+        .map(|pair| (pair[0], u8::try_from(pair[1]).unwrap_or(u8::MAX)))
+        .collect()
+}
+
+fn quantities(cards: &[CardQuantity]) -> Vec<(u32, u8)> {
+    cards
+        .iter()
+        .map(|card| (card.card_id, card.quantity))
+        .collect()
+}
+
+/// Deck of the Arena client, with its cards given as Arena ids
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArenaDeck {
+    pub id: String,
+    pub name: String,
+    pub companion: Option<u32>,
+    pub main: Vec<(u32, u8)>,
+    pub side: Vec<(u32, u8)>,
+}
+
+impl From<LegacyDeck> for ArenaDeck {
+    fn from(deck: LegacyDeck) -> Self {
+        Self {
+            id: deck.id,
+            name: deck.name,
+            companion: (deck.companion != 0).then_some(deck.companion),
+            main: id_amount_pairs(&deck.main_deck),
+            side: id_amount_pairs(&deck.sideboard),
+        }
+    }
+}
+
+/// Decks of the `StartHook` reply, which are listed apart from their names
+fn start_hook_decks(value: &Value) -> Result<Option<Vec<ArenaDeck>>> {
+    let (Some(summaries), Some(decks)) = (value.get("DeckSummariesV2"), value.get("Decks")) else {
+        return Ok(None);
+    };
+    let summaries: Vec<DeckSummary> = serde_json::from_value(summaries.clone())?;
+    let mut decks: HashMap<String, DeckCards> = serde_json::from_value(decks.clone())?;
+    let decks = summaries
+        .into_iter()
+        .filter_map(|summary| {
+            let cards = decks.remove(&summary.deck_id)?;
+            Some(ArenaDeck {
+                id: summary.deck_id,
+                name: summary.name,
+                companion: cards.companions.first().map(|card| card.card_id),
+                main: quantities(&cards.main_deck),
+                side: quantities(&cards.sideboard),
+            })
+        })
+        .collect();
+    Ok(Some(decks))
+}
+
+/// A game of the log, with the deck given as Arena ids
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArenaGame {
+    pub match_id: String,
+    pub game_number: u8,
+    /// Arena deck submitted to the event, when the log holds it
+    pub deck_id: Option<String>,
+    /// Arena ids of the main deck, once per copy
+    pub deck_cards: Vec<u32>,
+    pub on_the_play: Option<bool>,
+    /// Colors of the opponent's cards seen during the game, in WUBRG order
+    pub opponent_colors: String,
+    pub result: GameResult,
+}
+
+#[derive(Debug)]
+struct GameInProgress {
+    match_id: String,
+    game_number: u8,
+    on_the_play: Option<bool>,
+    opponent_colors: String,
+    over: bool,
+}
+
+/// Follows games through the messages of the game server. The deck is
+/// submitted once per match, later games of the match reuse it.
+#[derive(Debug, Default)]
+struct GameTracker {
+    /// Deck last submitted to an event, for the next match to start
+    deck_id: Option<String>,
+    /// Deck of the match in progress
+    match_deck_id: Option<String>,
+    seat: Option<u64>,
+    deck_cards: Vec<u32>,
+    game: Option<GameInProgress>,
+}
+
+impl GameTracker {
+    fn on_event(&mut self, event: &Value, games: &mut Vec<ArenaGame>) {
+        let messages = event["greToClientMessages"]
+            .as_array()
+            .into_iter()
+            .flatten();
+        for message in messages {
+            match message["type"].as_str() {
+                Some("GREMessageType_ConnectResp") => {
+                    self.seat = message["systemSeatIds"][0].as_u64();
+                    self.deck_cards = message["connectResp"]["deckMessage"]["deckCards"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|id| u32::try_from(id.as_u64()?).ok())
+                        .collect();
+                }
+                Some("GREMessageType_GameStateMessage") => {
+                    if let Some(game) = self.on_game_state(&message["gameStateMessage"]) {
+                        games.push(game);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns the game once it is over
+    fn on_game_state(&mut self, state: &Value) -> Option<ArenaGame> {
+        let seat = self.seat?;
+        let info = &state["gameInfo"];
+        if let (Some(match_id), Some(game_number)) =
+            (info["matchID"].as_str(), info["gameNumber"].as_u64())
+        {
+            let game_number = u8::try_from(game_number).unwrap_or(u8::MAX);
+            let is_new = self
+                .game
+                .as_ref()
+                .iter()
+                .all(|game| game.match_id != match_id || game.game_number != game_number);
+            if is_new {
+                let new_match = self
+                    .game
+                    .as_ref()
+                    .is_none_or(|game| game.match_id != match_id);
+                if new_match {
+                    self.match_deck_id = self.deck_id.take();
+                }
+                self.game = Some(GameInProgress {
+                    match_id: match_id.to_owned(),
+                    game_number,
+                    on_the_play: None,
+                    opponent_colors: String::new(),
+                    over: false,
+                });
+            }
+        }
+        let game = self.game.as_mut().filter(|game| !game.over)?;
+
+        let turn = &state["turnInfo"];
+        if game.on_the_play.is_none() && turn["turnNumber"].as_u64() == Some(1) {
+            game.on_the_play = turn["activePlayer"].as_u64().map(|active| active == seat);
+        }
+        let objects = state["gameObjects"].as_array().into_iter().flatten();
+        for object in objects.filter(|object| object["ownerSeatId"].as_u64() != Some(seat)) {
+            for color in object["color"].as_array().into_iter().flatten() {
+                let color = match color.as_str() {
+                    Some("CardColor_White") => 'W',
+                    Some("CardColor_Blue") => 'U',
+                    Some("CardColor_Black") => 'B',
+                    Some("CardColor_Red") => 'R',
+                    Some("CardColor_Green") => 'G',
+                    _ => continue,
+                };
+                if !game.opponent_colors.contains(color) {
+                    game.opponent_colors.push(color);
+                }
+            }
+        }
+
+        if info["stage"] != "GameStage_GameOver" {
+            return None;
+        }
+        let result = info["results"]
+            .as_array()?
+            .iter()
+            .rev()
+            .find(|result| result["scope"] == "MatchScope_Game")?;
+        // Teams are seats in games of two players
+        let result = if result["result"] == "ResultType_Draw" {
+            GameResult::Draw
+        } else if result["winningTeamId"].as_u64() == Some(seat) {
+            GameResult::Win
+        } else {
+            GameResult::Loss
+        };
+        game.over = true;
+        Some(ArenaGame {
+            match_id: game.match_id.clone(),
+            game_number: game.game_number,
+            deck_id: self.match_deck_id.clone(),
+            deck_cards: self.deck_cards.clone(),
+            on_the_play: game.on_the_play,
+            opponent_colors: "WUBRG"
+                .chars()
+                .filter(|&color| game.opponent_colors.contains(color))
+                .collect(),
+            result,
+        })
+    }
+}
+
+/// Currencies of the account
+#[derive(Debug, Clone, Default)]
+pub struct ArenaInventory {
+    pub gems: u32,
+    pub gold: u32,
+    pub wildcards: Wildcards,
+}
+
+impl From<InventoryInfo> for ArenaInventory {
+    #[allow(clippy::cast_precision_loss)]
+    fn from(info: InventoryInfo) -> Self {
+        Self {
+            gems: info.gems,
+            gold: info.gold,
+            wildcards: Wildcards {
+                common: info.wild_card_commons as f32,
+                uncommon: info.wild_card_uncommons as f32,
+                rare: info.wild_card_rares as f32,
+                mythic: info.wild_card_mythics as f32,
+                vault_progress: info.total_vault_progress / 10.0,
+            },
+        }
+    }
+}
+
+impl From<LegacyInventory> for ArenaInventory {
+    #[allow(clippy::cast_precision_loss)]
+    fn from(inventory: LegacyInventory) -> Self {
+        Self {
+            gems: inventory.gems,
+            gold: inventory.gold,
+            wildcards: Wildcards {
+                common: inventory.wc_common as f32,
+                uncommon: inventory.wc_uncommon as f32,
+                rare: inventory.wc_rare as f32,
+                mythic: inventory.wc_mythic as f32,
+                vault_progress: inventory.vault_progress,
+            },
+        }
+    }
+}
+
+/// What the game wrote to `Player.log` about the account, when detailed logs
+/// are enabled in its options. When the log holds several replies, the last
+/// one wins.
+#[derive(Debug, Clone, Default)]
+pub struct PlayerLog {
+    /// Copies owned, by Arena id
+    pub cards: Option<HashMap<u32, u8>>,
+    pub inventory: Option<ArenaInventory>,
+    pub decks: Vec<ArenaDeck>,
+    /// Games played since the game was launched
+    pub games: Vec<ArenaGame>,
+    /// Why replies that could not be read were skipped
+    pub skipped: Vec<String>,
+}
+
+/// The first JSON object of a line, ignoring what comes before and after it
+fn json_object(line: &str) -> Option<Value> {
+    let start = line.find('{')?;
+    serde_json::Deserializer::from_str(&line[start..])
+        .into_iter::<Value>()
+        .next()?
+        .ok()
+}
+
+/// Replies are either the whole JSON object, or wrapped in a `payload` field,
+/// sometimes as a string holding the JSON
+fn payload(value: Value) -> Value {
+    match value {
+        Value::Object(mut object) => match object.remove("payload") {
+            Some(Value::String(payload)) => serde_json::from_str(&payload).unwrap_or_default(),
+            Some(payload) => payload,
+            None => Value::Object(object),
+        },
+        value => value,
+    }
+}
+
+impl PlayerLog {
+    /// Where the game writes its log on Windows, or on macOS
+    #[must_use]
+    pub fn default_path() -> Option<PathBuf> {
+        let base_dirs = BaseDirs::new()?;
+        let log_dir = if cfg!(target_os = "macos") {
+            "Library/Logs/Wizards Of The Coast/MTGA"
+        } else {
+            "AppData/LocalLow/Wizards Of The Coast/MTGA"
+        };
+        Some(base_dirs.home_dir().join(log_dir).join("Player.log"))
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let log = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read game log {}", path.display()))?;
+        Self::parse(&log)
+    }
+
+    #[allow(clippy::missing_panics_doc)]
+    pub fn parse(log: &str) -> Result<Self> {
+        let mut player_log = Self::default();
+        let mut pending_marker = None;
+        let mut tracker = GameTracker::default();
+        // Decks are submitted to events in requests, whose payload is escaped
+        let submitted_deck = Regex::new(r#"(?i)deckid\\?"\s*:\s*\\?"([0-9a-f-]+)"#)
+            .expect("Pattern should be valid");
+        for line in log.lines() {
+            if line.contains("==>") && line.contains("SetDeck") {
+                if let Some(captures) = submitted_deck.captures(line) {
+                    tracker.deck_id = Some(captures[1].to_owned());
+                }
+                continue;
+            }
+            let marker = [
+                CARDS_MARKER,
+                LEGACY_INVENTORY_MARKER,
+                LEGACY_DECK_LISTS_MARKER,
+            ]
+            .into_iter()
+            .find(|marker| line.contains(marker));
+            // Requests are logged too, only replies (`<==`) matter
+            if marker.is_some() && line.contains("==>") {
+                pending_marker = None;
+                continue;
+            }
+            if marker.is_some() {
+                pending_marker = marker;
+            }
+            let Some(value) = json_object(line) else {
+                continue;
+            };
+            let marker = pending_marker.take();
+            if let Err(err) = player_log.read_reply(marker, value, &mut tracker) {
+                player_log.skipped.push(format!("{err:#}"));
+            }
+        }
+        Ok(player_log)
+    }
+
+    /// Reads a reply of the game, or an event of a game in progress
+    fn read_reply(
+        &mut self,
+        marker: Option<&str>,
+        value: Value,
+        tracker: &mut GameTracker,
+    ) -> Result<()> {
+        match marker {
+            Some(CARDS_MARKER) => {
+                let cards = serde_json::from_value::<HashMap<String, u8>>(payload(value))
+                    .context("Failed to parse the card collection of the game log")?
+                    .into_iter()
+                    .map(|(id, amount)| Ok((id.parse()?, amount)))
+                    .collect::<Result<_>>()
+                    .context("Failed to parse the card ids of the game log")?;
+                self.cards = Some(cards);
+            }
+            Some(LEGACY_INVENTORY_MARKER) => {
+                let inventory: LegacyInventory = serde_json::from_value(payload(value))
+                    .context("Failed to parse the inventory of the game log")?;
+                self.inventory = Some(inventory.into());
+            }
+            Some(_) => {
+                let decks: Vec<LegacyDeck> = serde_json::from_value(payload(value))
+                    .context("Failed to parse the decks of the game log")?;
+                self.decks = decks.into_iter().map(ArenaDeck::from).collect();
+            }
+            None => {
+                if let Some(info) = value.get("InventoryInfo") {
+                    let info: InventoryInfo = serde_json::from_value(info.clone())
+                        .context("Failed to parse the inventory of the game log")?;
+                    self.inventory = Some(info.into());
+                }
+                if let Some(decks) =
+                    start_hook_decks(&value).context("Failed to parse the decks of the game log")?
+                {
+                    self.decks = decks;
+                }
+                if let Some(event) = value.get("greToClientEvent") {
+                    tracker.on_event(event, &mut self.games);
+                }
+            }
+        }
+        Ok(())
+    }
+}
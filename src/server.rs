@@ -0,0 +1,406 @@
+use crate::{
+    config::Config,
+    cost_model::CostModel,
+    craft_suggester::CraftRecommender,
+    suggestions::{suggestions, SuggestionFilter},
+    Deck, Inventory, Rarity, Roster,
+};
+use anyhow::{bail, Result};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tiny_http::{Header, Method, Response, Server};
+
+/// Error of a request, sent back as `{"error": message}`
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl ApiError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn bad_request(err: &anyhow::Error) -> Self {
+        Self::new(400, format!("{err:#}"))
+    }
+
+    fn not_found(err: &anyhow::Error) -> Self {
+        Self::new(404, format!("{err:#}"))
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(err: anyhow::Error) -> Self {
+        Self::new(500, format!("{err:#}"))
+    }
+}
+
+type ApiResult = Result<(u16, Value), ApiError>;
+
+fn ok(value: &impl Serialize) -> ApiResult {
+    Ok((
+        200,
+        serde_json::to_value(value).map_err(anyhow::Error::from)?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+struct NewDeck {
+    name: String,
+    decklist: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    auto_fix: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct EditedDeck {
+    decklist: String,
+    #[serde(default)]
+    auto_fix: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct DeckSummary<'d> {
+    name: &'d str,
+    tags: Vec<&'d String>,
+    /// `None` when the deck plays cards we know nothing of
+    cost: Option<f32>,
+}
+
+#[derive(Debug, Serialize)]
+struct DeckDetails<'d> {
+    #[serde(flatten)]
+    summary: DeckSummary<'d>,
+    decklist: String,
+    rebalanced: Vec<&'d String>,
+}
+
+#[derive(Debug, Serialize)]
+struct MissingCard<'d> {
+    card: &'d str,
+    amount: u8,
+    rarity: Rarity,
+    set: &'d str,
+}
+
+#[derive(Debug, Serialize)]
+struct SuggestedCard<'d> {
+    card: &'d str,
+    rarity: Rarity,
+    score: f32,
+    share: f32,
+}
+
+/// Decodes `%XX` escapes, and `+` as a space when `plus_as_space` is set, as
+/// in query strings
+fn percent_decode(s: &str, plus_as_space: bool) -> Result<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+    while let Some(byte) = iter.next() {
+        match byte {
+            b'%' => {
+                let (Some(high), Some(low)) = (iter.next(), iter.next()) else {
+                    bail!("Truncated escape sequence in `{s}`");
+                };
+                let hex = [high, low];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex)?, 16)?);
+            }
+            b'+' if plus_as_space => bytes.push(b' '),
+            _ => bytes.push(byte),
+        }
+    }
+    Ok(String::from_utf8(bytes)?)
+}
+
+fn parse_query(query: &str) -> Result<HashMap<String, String>> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Ok((percent_decode(key, true)?, percent_decode(value, true)?))
+        })
+        .collect()
+}
+
+fn query_param<T: std::str::FromStr>(
+    query: &HashMap<String, String>,
+    key: &str,
+) -> Result<Option<T>, ApiError>
+where
+    T::Err: std::fmt::Display,
+{
+    query
+        .get(key)
+        .map(|value| {
+            value.parse().map_err(|err| {
+                ApiError::new(400, format!("Invalid value `{value}` for `{key}`: {err}"))
+            })
+        })
+        .transpose()
+}
+
+fn query_list(query: &HashMap<String, String>, key: &str) -> Vec<String> {
+    query
+        .get(key)
+        .map(|value| value.split(',').map(str::to_owned).collect())
+        .unwrap_or_default()
+}
+
+/// JSON API over the roster, for `detr serve`. Requests are handled one at a
+/// time, and the roster is written after every change.
+pub struct Api<'a> {
+    roster: &'a mut Roster,
+    inventory: &'a mut Inventory,
+    config: &'a Config,
+    ignore_sideboard: bool,
+}
+
+impl<'a> Api<'a> {
+    pub fn new(
+        roster: &'a mut Roster,
+        inventory: &'a mut Inventory,
+        config: &'a Config,
+        ignore_sideboard: bool,
+    ) -> Self {
+        Self {
+            roster,
+            inventory,
+            config,
+            ignore_sideboard,
+        }
+    }
+
+    /// Answers requests until the server is unblocked
+    #[allow(clippy::missing_panics_doc)]
+    pub fn serve(&mut self, server: &Server) {
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+            let result = match request.as_reader().read_to_string(&mut body) {
+                Ok(_) => self.handle(request.method(), request.url(), &body),
+                Err(err) => Err(ApiError::new(400, format!("Failed to read body: {err}"))),
+            };
+            let (status, value) =
+                result.unwrap_or_else(|err| (err.status, json!({ "error": err.message })));
+            let content_type = Header::from_bytes("Content-Type", "application/json")
+                .expect("Header should be valid");
+            let response = Response::from_string(value.to_string())
+                .with_status_code(status)
+                .with_header(content_type);
+            if let Err(err) = request.respond(response) {
+                eprintln!("Failed to respond to request: {err}");
+            }
+        }
+    }
+
+    pub fn handle(&mut self, method: &Method, url: &str, body: &str) -> ApiResult {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let query = parse_query(query).map_err(|err| ApiError::bad_request(&err))?;
+        let segments = path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| percent_decode(segment, false))
+            .collect::<Result<Vec<_>>>()
+            .map_err(|err| ApiError::bad_request(&err))?;
+        let segments = segments.iter().map(String::as_str).collect_vec();
+        match (method, segments.as_slice()) {
+            (Method::Get, ["roster"]) => ok(&self.roster.decks().collect_vec()),
+            (Method::Get, ["decks"]) => self.decks(),
+            (Method::Post, ["decks"]) => self.add_deck(body),
+            (Method::Get, ["decks", name]) => self.deck(name),
+            (Method::Put, ["decks", name]) => self.edit_deck(name, body),
+            (Method::Delete, ["decks", name]) => self.remove_deck(name),
+            (Method::Get, ["decks", name, "missing"]) => self.missing(name),
+            (Method::Get, ["suggestions"]) => self.suggestions(&query),
+            (Method::Get, ["recommendations"]) => self.recommendations(&query),
+            _ => Err(ApiError::new(
+                404,
+                format!("No endpoint for {method} {path}"),
+            )),
+        }
+    }
+
+    fn summary<'d>(&self, deck: &'d Deck) -> DeckSummary<'d> {
+        DeckSummary {
+            name: &deck.name,
+            tags: deck.tags().collect(),
+            cost: self
+                .inventory
+                .deck_cost(deck, self.ignore_sideboard, &self.config.deck_cost)
+                .ok(),
+        }
+    }
+
+    fn find(&self, deck_name: &str) -> Result<&Deck, ApiError> {
+        self.roster
+            .find(deck_name)
+            .map_err(|err| ApiError::not_found(&err))
+    }
+
+    fn decks(&self) -> ApiResult {
+        ok(&self
+            .roster
+            .decks()
+            .map(|deck| self.summary(deck))
+            .collect_vec())
+    }
+
+    fn deck(&self, deck_name: &str) -> ApiResult {
+        let deck = self.find(deck_name)?;
+        ok(&DeckDetails {
+            summary: self.summary(deck),
+            decklist: deck.to_string(),
+            rebalanced: deck.rebalanced_cards().collect(),
+        })
+    }
+
+    /// Parses a decklist, and makes sure we know every card it plays
+    fn parse_deck(&mut self, name: &str, decklist: &str, auto_fix: bool) -> Result<Deck, ApiError> {
+        let mut deck = decklist
+            .parse::<Deck>()
+            .map_err(|err| ApiError::bad_request(&err))?
+            .name(name);
+        self.inventory
+            .resolve_names(&mut deck, auto_fix)
+            .map_err(|err| ApiError::new(422, format!("{err:#}")))?;
+        Ok(deck)
+    }
+
+    fn add_deck(&mut self, body: &str) -> ApiResult {
+        let new_deck: NewDeck =
+            serde_json::from_str(body).map_err(|err| ApiError::bad_request(&err.into()))?;
+        if self.roster.find(&new_deck.name).is_ok() {
+            return Err(ApiError::new(
+                409,
+                format!("A deck named `{}` already exists", new_deck.name),
+            ));
+        }
+        let mut deck = self.parse_deck(&new_deck.name, &new_deck.decklist, new_deck.auto_fix)?;
+        for tag in &new_deck.tags {
+            deck.add_tag(tag);
+        }
+        self.roster.add_deck(deck);
+        self.roster.write()?;
+        let (_, value) = self.deck(&new_deck.name)?;
+        Ok((201, value))
+    }
+
+    fn edit_deck(&mut self, deck_name: &str, body: &str) -> ApiResult {
+        let edited: EditedDeck =
+            serde_json::from_str(body).map_err(|err| ApiError::bad_request(&err.into()))?;
+        self.find(deck_name)?;
+        let deck = self.parse_deck(deck_name, &edited.decklist, edited.auto_fix)?;
+        let deck = deck.with_metadata_of(self.find(deck_name)?);
+        self.roster.replace(deck_name, deck)?;
+        self.roster.write()?;
+        self.deck(deck_name)
+    }
+
+    fn remove_deck(&mut self, deck_name: &str) -> ApiResult {
+        self.roster
+            .remove_deck(deck_name)
+            .map_err(|err| ApiError::not_found(&err))?;
+        self.roster.write()?;
+        Ok((200, json!({ "removed": deck_name })))
+    }
+
+    fn missing(&self, deck_name: &str) -> ApiResult {
+        let deck = self.find(deck_name)?;
+        let missing = self
+            .inventory
+            .missing(deck, self.ignore_sideboard)?
+            .into_iter()
+            .map(|(card, amount, rarity, set)| MissingCard {
+                card,
+                amount,
+                rarity,
+                set,
+            })
+            .collect_vec();
+        ok(&missing)
+    }
+
+    /// Accepts the `top`, `rarity`, `set`, `tag` and `equally` parameters of
+    /// `suggest`
+    fn suggestions(&self, query: &HashMap<String, String>) -> ApiResult {
+        let rarities = query_list(query, "rarity")
+            .iter()
+            .map(|rarity| rarity.parse())
+            .collect::<Result<Vec<Rarity>>>()
+            .map_err(|err| ApiError::bad_request(&err))?;
+        let filter = SuggestionFilter {
+            rarities: &rarities,
+            set: query.get("set").map(String::as_str),
+            tag: query.get("tag").map(String::as_str),
+            never_craft: &self.config.never_craft,
+        };
+        let equally = query_param(query, "equally")?.unwrap_or(false);
+        let suggestions = suggestions(
+            self.roster,
+            &*self.inventory,
+            self.ignore_sideboard,
+            (!equally).then_some(&self.config.deck_cost),
+            filter,
+        )?;
+        let top = query_param(query, "top")?.unwrap_or(usize::MAX);
+        let suggestions = suggestions
+            .iter()
+            .take(top)
+            .map(|s| SuggestedCard {
+                card: s.card,
+                rarity: s.rarity,
+                score: s.score,
+                share: s.share,
+            })
+            .collect_vec();
+        ok(&suggestions)
+    }
+
+    /// Accepts the `rares`, `mythics` and `with` parameters of `recommend`
+    fn recommendations(&self, query: &HashMap<String, String>) -> ApiResult {
+        let (Some(rares), Some(mythics)) =
+            (query_param(query, "rares")?, query_param(query, "mythics")?)
+        else {
+            return Err(ApiError::new(
+                400,
+                "Both `rares` and `mythics` must be given",
+            ));
+        };
+        let with = query_list(query, "with");
+        for deck_name in &with {
+            self.find(deck_name)?;
+        }
+        let recommender = CraftRecommender::new(
+            rares,
+            mythics,
+            self.ignore_sideboard,
+            (!with.is_empty()).then_some(with),
+            self.roster,
+            &*self.inventory,
+        );
+        ok(&recommender.recommend()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_urls() {
+        assert_eq!(percent_decode("Mono%20Red%2B", false).unwrap(), "Mono Red+");
+        let query = parse_query("rarity=r,m&tag=best+of+three").unwrap();
+        assert_eq!(query["rarity"], "r,m");
+        assert_eq!(query["tag"], "best of three");
+        assert!(percent_decode("%2", false).is_err());
+    }
+}
@@ -0,0 +1,375 @@
+use crate::{cost_model::CostModel, Deck, Sideboard};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::{self, File},
+    path::Path,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const STANDARD: &str = "standard";
+
+/// A calendar day, stored as days since 1970-01-01 and written `YYYY-MM-DD`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Date(i64);
+
+impl Date {
+    /// From the proleptic Gregorian calendar, after Howard Hinnant's
+    /// `days_from_civil`
+    #[must_use]
+    pub fn new(year: i64, month: u32, day: u32) -> Self {
+        let (month, day) = (i64::from(month), i64::from(day));
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        Self(era * 146_097 + day_of_era - 719_468)
+    }
+
+    #[must_use]
+    pub fn today() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        Self(i64::try_from(seconds / 86_400).unwrap_or_default())
+    }
+
+    /// Year, month and day, after Howard Hinnant's `civil_from_days`
+    #[must_use]
+    pub fn civil(self) -> (i64, i64, i64) {
+        let days = self.0 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        (year, month, day)
+    }
+
+    #[must_use]
+    pub const fn days_until(self, other: Self) -> i64 {
+        other.0 - self.0
+    }
+}
+
+impl FromStr for Date {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let context = || format!("Expected a date like `2024-08-02`, found `{s}`");
+        let mut parts = s.trim().splitn(3, '-');
+        let mut next = || -> Result<i64> { Ok(parts.next().with_context(context)?.parse()?) };
+        let (year, month, day) = (next()?, next()?, next()?);
+        let (Ok(month), Ok(day)) = (u32::try_from(month), u32::try_from(day)) else {
+            bail!(context());
+        };
+        let date = Self::new(year, month, day);
+        if date.civil() != (year, i64::from(month), i64::from(day)) {
+            bail!(context());
+        }
+        Ok(date)
+    }
+}
+
+impl TryFrom<String> for Date {
+    type Error = anyhow::Error;
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Date> for String {
+    fn from(date: Date) -> Self {
+        date.to_string()
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = self.civil();
+        write!(f, "{year:04}-{month:02}-{day:02}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SetInfo {
+    /// Set code, as scryfall and the collection write it
+    pub code: String,
+    pub name: String,
+    pub released: Date,
+    /// Formats the set entered. Rotations take it out of Standard.
+    pub formats: Vec<String>,
+}
+
+/// Sets leaving Standard on a given day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rotation {
+    pub date: Date,
+    pub sets: Vec<String>,
+}
+
+/// Set metadata and the Standard rotation schedule. Upcoming rotations are
+/// announced by Wizards of the Coast, and have to be added to the file as they
+/// are.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SetCalendar {
+    pub sets: Vec<SetInfo>,
+    pub rotations: Vec<Rotation>,
+}
+
+impl Default for SetCalendar {
+    fn default() -> Self {
+        const STANDARD_SETS: [(&str, &str, &str); 18] = [
+            ("dmu", "Dominaria United", "2022-09-09"),
+            ("bro", "The Brothers' War", "2022-11-18"),
+            ("one", "Phyrexia: All Will Be One", "2023-02-10"),
+            ("mom", "March of the Machine", "2023-04-21"),
+            ("mat", "March of the Machine: The Aftermath", "2023-05-12"),
+            ("woe", "Wilds of Eldraine", "2023-09-08"),
+            ("lci", "The Lost Caverns of Ixalan", "2023-11-17"),
+            ("mkm", "Murders at Karlov Manor", "2024-02-09"),
+            ("otj", "Outlaws of Thunder Junction", "2024-04-19"),
+            ("blb", "Bloomburrow", "2024-08-02"),
+            ("dsk", "Duskmourn: House of Horror", "2024-09-27"),
+            ("fdn", "Foundations", "2024-11-15"),
+            ("dft", "Aetherdrift", "2025-02-14"),
+            ("tdm", "Tarkir: Dragonstorm", "2025-04-11"),
+            ("fin", "Final Fantasy", "2025-06-13"),
+            ("eoe", "Edge of Eternities", "2025-08-01"),
+            ("spm", "Marvel's Spider-Man", "2025-09-26"),
+            ("tla", "Avatar: The Last Airbender", "2025-11-21"),
+        ];
+        let date = |date: &str| date.parse().expect("Invalid built-in date");
+        let sets = STANDARD_SETS
+            .into_iter()
+            .map(|(code, name, released)| SetInfo {
+                code: code.to_owned(),
+                name: name.to_owned(),
+                released: date(released),
+                formats: [STANDARD, "alchemy", "explorer", "historic", "timeless"]
+                    .map(str::to_owned)
+                    .to_vec(),
+            })
+            .collect();
+        let rotations = vec![Rotation {
+            date: date("2024-08-02"),
+            sets: ["dmu", "bro", "one", "mom", "mat"]
+                .map(str::to_owned)
+                .to_vec(),
+        }];
+        Self { sets, rotations }
+    }
+}
+
+impl SetCalendar {
+    /// Reads the calendar, falling back to the built-in one if the file does
+    /// not exist
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        serde_json::from_reader(File::open(path)?)
+            .with_context(|| format!("Failed to parse set calendar {}", path.display()))
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn set(&self, code: &str) -> Option<&SetInfo> {
+        self.sets.iter().find(|set| set.code == code)
+    }
+
+    /// The day a set leaves Standard, if it is scheduled
+    #[must_use]
+    pub fn rotation_of(&self, code: &str) -> Option<Date> {
+        self.rotations
+            .iter()
+            .filter(|rotation| rotation.sets.iter().any(|set| set == code))
+            .map(|rotation| rotation.date)
+            .min()
+    }
+
+    /// Whether the set is legal in Standard on that day. Unknown sets are not.
+    #[must_use]
+    pub fn is_standard(&self, code: &str, date: Date) -> bool {
+        self.set(code).is_some_and(|set| {
+            set.released <= date
+                && set.formats.iter().any(|format| format == STANDARD)
+                && self
+                    .rotation_of(code)
+                    .iter()
+                    .all(|rotation| date < *rotation)
+        })
+    }
+
+    /// The first rotation after `today`, if the calendar has one
+    #[must_use]
+    pub fn next_rotation(&self, today: Date) -> Option<&Rotation> {
+        self.rotations
+            .iter()
+            .filter(|rotation| today < rotation.date)
+            .min_by_key(|rotation| rotation.date)
+    }
+
+    /// The first rotation after `today`, if it is at most `days` away
+    #[must_use]
+    pub fn upcoming_rotation(&self, today: Date, days: u32) -> Option<&Rotation> {
+        self.next_rotation(today)
+            .filter(|rotation| today.days_until(rotation.date) <= i64::from(days))
+    }
+
+    /// Share of a year of Standard play a set has left, scaled so that sets
+    /// with `horizon` days or more left, or no scheduled rotation, keep their
+    /// whole value
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn standard_share(&self, code: &str, today: Date, horizon: u32) -> f32 {
+        match self.rotation_of(code) {
+            Some(rotation) if self.is_standard(code, today) => {
+                let left = today.days_until(rotation) as f32;
+                (left / horizon.max(1) as f32).clamp(0.0, 1.0)
+            }
+            _ => 1.0,
+        }
+    }
+}
+
+/// How far ahead rotations are announced in `list` and `booster`, and how
+/// early `booster` starts discounting the sets they take out of Standard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RotationConfig {
+    pub warning_days: u32,
+    pub discount_days: u32,
+}
+
+impl Default for RotationConfig {
+    fn default() -> Self {
+        Self {
+            warning_days: 90,
+            discount_days: 365,
+        }
+    }
+}
+
+/// What a rotation does to a deck
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RotatingCards<'d> {
+    /// Cards leaving Standard, with no reprint in a set that stays
+    pub cards: Vec<&'d String>,
+    /// Cards with no print in a set of the calendar, most likely from a set
+    /// newer than it, which could not be checked
+    pub unchecked: Vec<&'d String>,
+}
+
+/// Cards of a Standard deck that the upcoming rotation takes out of Standard.
+/// Prints from sets missing from the calendar are left out. Decks with a card
+/// that is not legal in Standard today have none.
+pub fn rotating_cards<'d>(
+    deck: &'d Deck,
+    model: &impl CostModel,
+    calendar: &SetCalendar,
+    today: Date,
+    rotation: &Rotation,
+) -> Result<RotatingCards<'d>> {
+    let mut rotating = RotatingCards::default();
+    for (card_name, _) in deck.cards(Sideboard::Counted) {
+        let sets = model
+            .prints(card_name)?
+            .iter()
+            .map(|(_, _, set)| set)
+            .filter(|set| calendar.set(set).is_some())
+            .collect::<Vec<_>>();
+        if sets.is_empty() {
+            rotating.unchecked.push(card_name);
+            continue;
+        }
+        let legal_on = |date| sets.iter().any(|set| calendar.is_standard(set, date));
+        if !legal_on(today) {
+            return Ok(RotatingCards::default());
+        }
+        if !legal_on(rotation.date) {
+            rotating.cards.push(card_name);
+        }
+    }
+    rotating.cards.sort();
+    rotating.unchecked.sort();
+    Ok(rotating)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cost_model::FakeCollection, Rarity};
+
+    #[test]
+    fn dates() -> Result<()> {
+        let date: Date = "2024-02-29".parse()?;
+        assert_eq!(date.to_string(), "2024-02-29");
+        assert_eq!(Date::new(1970, 1, 1), Date(0));
+        assert_eq!(date.days_until("2025-03-01".parse()?), 366);
+        assert!("2023-02-29".parse::<Date>().is_err());
+        assert_eq!(serde_json::to_string(&date)?, r#""2024-02-29""#);
+        Ok(())
+    }
+
+    #[test]
+    fn rotation() -> Result<()> {
+        let mut calendar = SetCalendar::default();
+        calendar.rotations.push(Rotation {
+            date: "2027-01-01".parse()?,
+            sets: vec!["woe".to_owned()],
+        });
+        let today = "2026-11-01".parse()?;
+        let rotation = calendar.upcoming_rotation(today, 90).unwrap().clone();
+        assert!(calendar.upcoming_rotation(today, 30).is_none());
+        assert_eq!(calendar.next_rotation(today), Some(&rotation));
+        assert!(calendar.next_rotation(rotation.date).is_none());
+        assert!(calendar.is_standard("woe", today));
+        assert!(!calendar.is_standard("dmu", today));
+        assert!((calendar.standard_share("woe", today, 365) - 61.0 / 365.0).abs() < 1e-6);
+        assert!((calendar.standard_share("fdn", today, 365) - 1.0).abs() < f32::EPSILON);
+
+        let collection = FakeCollection::new(&[
+            ("Mountain", 4, Rarity::Rare, "fdn"),
+            ("Virtue of Courage", 4, Rarity::Rare, "woe"),
+            ("Lightning Strike", 4, Rarity::Rare, "woe"),
+            ("Lightning Strike", 4, Rarity::Rare, "fdn"),
+            ("Sheoldred, the Apocalypse", 4, Rarity::Rare, "dmu"),
+            ("Shock", 4, Rarity::Common, "fdn"),
+            ("Shock", 4, Rarity::Common, "new"),
+            ("Novel Card", 4, Rarity::Rare, "new"),
+        ]);
+        let deck: Deck = "Deck\n20 Mountain\n4 Virtue of Courage\n4 Lightning Strike\n".parse()?;
+        let rotating = rotating_cards(&deck, &collection, &calendar, today, &rotation)?;
+        assert_eq!(rotating.cards, ["Virtue of Courage"]);
+        assert!(rotating.unchecked.is_empty());
+        let deck: Deck = "Deck\n20 Mountain\n4 Sheoldred, the Apocalypse\n".parse()?;
+        let rotating = rotating_cards(&deck, &collection, &calendar, today, &rotation)?;
+        assert_eq!(rotating, RotatingCards::default());
+
+        // Sets the calendar doesn't know don't take the deck out of Standard
+        let deck: Deck =
+            "Deck\n20 Mountain\n4 Virtue of Courage\n4 Shock\n4 Novel Card\n".parse()?;
+        let rotating = rotating_cards(&deck, &collection, &calendar, today, &rotation)?;
+        assert_eq!(rotating.cards, ["Virtue of Courage"]);
+        assert_eq!(rotating.unchecked, ["Novel Card"]);
+        Ok(())
+    }
+}
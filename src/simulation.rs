@@ -0,0 +1,248 @@
+use crate::{odds::OPENING_HAND, Deck, Zone};
+use anyhow::{bail, Context, Result};
+use rand::{seq::SliceRandom, Rng};
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+/// The main deck, one entry per copy
+#[must_use]
+pub fn library(deck: &Deck) -> Vec<&String> {
+    deck.cards_by_zone()
+        .filter(|(zone, _, _)| *zone == Zone::Main)
+        .flat_map(|(_, name, amount)| std::iter::repeat_n(name, usize::from(amount)))
+        .collect()
+}
+
+/// A random opening hand
+pub fn draw_hand<'d>(library: &[&'d String], rng: &mut impl Rng) -> Vec<&'d String> {
+    library
+        .choose_multiple(rng, OPENING_HAND as usize)
+        .copied()
+        .collect()
+}
+
+/// Which hands to keep, with the London mulligan: after each mulligan, we
+/// draw 7 cards and put one more on the bottom of the library. The hand is
+/// kept if the cards put on the bottom can leave it with a land count in
+/// range, or once it would be as small as `keep_at`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeepRule {
+    pub min_lands: u32,
+    pub max_lands: u32,
+    pub keep_at: u32,
+}
+
+impl KeepRule {
+    /// Lands left once the hand is kept, or `None` to mulligan again
+    fn lands_kept(&self, lands: u32, mulligans: u32) -> Option<u32> {
+        let size = OPENING_HAND - mulligans;
+        // Putting lands or spells on the bottom
+        let fewest = lands.saturating_sub(mulligans);
+        let most = lands.min(size);
+        if size <= self.keep_at.max(1) {
+            return Some(
+                lands
+                    .clamp(self.min_lands, self.max_lands)
+                    .clamp(fewest, most),
+            );
+        }
+        let low = fewest.max(self.min_lands);
+        let high = most.min(self.max_lands);
+        (low <= high).then(|| lands.clamp(low, high))
+    }
+}
+
+/// Land count range of the hands to keep, like `2-4`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LandRange(pub u32, pub u32);
+
+impl FromStr for LandRange {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (min, max) = s.split_once('-').unwrap_or((s, s));
+        let min = min
+            .trim()
+            .parse()
+            .context("Expected a land range like `2-4`")?;
+        let max = max
+            .trim()
+            .parse()
+            .context("Expected a land range like `2-4`")?;
+        if min > max || max > OPENING_HAND {
+            bail!("Expected a land range within 0-{OPENING_HAND}, found `{s}`");
+        }
+        Ok(Self(min, max))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Simulation {
+    pub trials: u32,
+    /// Trials by size of the kept hand
+    pub kept: BTreeMap<u32, u32>,
+    /// For each turn from the first, trials that made every land drop so far
+    pub on_curve: Vec<u32>,
+    /// For each turn from the first, the average number of lands played
+    pub lands_played: Vec<f64>,
+    /// For each turn from the first, trials by number of lands played so far
+    pub land_drops: Vec<Vec<u32>>,
+}
+
+/// Plays the first turns of many games, with lands and spells only
+pub fn simulate(
+    lands: &[bool],
+    rule: &KeepRule,
+    turns: u32,
+    on_the_play: bool,
+    trials: u32,
+    rng: &mut impl Rng,
+) -> Simulation {
+    let mut library = lands.to_vec();
+    let mut simulation = Simulation {
+        trials,
+        on_curve: vec![0; turns as usize],
+        lands_played: vec![0.0; turns as usize],
+        land_drops: vec![vec![0; turns as usize + 1]; turns as usize],
+        ..Simulation::default()
+    };
+    let hand_size = (OPENING_HAND as usize).min(library.len());
+    for _ in 0..trials {
+        let mut mulligans = 0;
+        let in_hand = loop {
+            library.shuffle(rng);
+            let lands = u32::try_from(library[..hand_size].iter().filter(|&&land| land).count())
+                .unwrap_or(OPENING_HAND);
+            if let Some(kept) = rule.lands_kept(lands, mulligans) {
+                break kept;
+            }
+            mulligans += 1;
+        };
+        *simulation.kept.entry(OPENING_HAND - mulligans).or_default() += 1;
+
+        let mut available = in_hand;
+        let mut played = 0;
+        let mut draws = library[hand_size..].iter();
+        for turn in 0..turns as usize {
+            if (turn > 0 || !on_the_play) && draws.next() == Some(&true) {
+                available += 1;
+            }
+            if available > played {
+                played += 1;
+            }
+            // At most one land a turn, so making this drop made the previous ones
+            if played as usize == turn + 1 {
+                simulation.on_curve[turn] += 1;
+            }
+            simulation.lands_played[turn] += f64::from(played);
+            simulation.land_drops[turn][played as usize] += 1;
+        }
+    }
+    for lands in &mut simulation.lands_played {
+        *lands /= f64::from(trials.max(1));
+    }
+    simulation
+}
+
+impl fmt::Display for Simulation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = |count: u32| f64::from(count) / f64::from(self.trials.max(1)) * 100.0;
+        writeln!(f, "Kept hands, over {} trials", self.trials)?;
+        for (size, count) in self.kept.iter().rev() {
+            writeln!(f, "  {size} cards\t {:.1}%", percent(*count))?;
+        }
+        write!(f, "\nTurn\t On curve\t Lands played")?;
+        for (turn, (on_curve, lands)) in self.on_curve.iter().zip(&self.lands_played).enumerate() {
+            write!(
+                f,
+                "\n{}\t {:.1}%\t\t {lands:.2}",
+                turn + 1,
+                percent(*on_curve)
+            )?;
+        }
+        write!(f, "\n\nLands played\nTurn")?;
+        for lands in 0..=self.land_drops.len() {
+            write!(f, "\t {lands}")?;
+        }
+        for (turn, drops) in self.land_drops.iter().enumerate() {
+            write!(f, "\n{}", turn + 1)?;
+            for count in drops {
+                write!(f, "\t {:.1}%", percent(*count))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const RULE: KeepRule = KeepRule {
+        min_lands: 2,
+        max_lands: 4,
+        keep_at: 5,
+    };
+
+    #[test]
+    fn london_mulligan() {
+        assert_eq!(RULE.lands_kept(3, 0), Some(3));
+        assert_eq!(RULE.lands_kept(1, 0), None);
+        // Two of six lands go to the bottom
+        assert_eq!(RULE.lands_kept(6, 1), None);
+        assert_eq!(RULE.lands_kept(6, 2), Some(4));
+        assert_eq!(RULE.lands_kept(0, 2), Some(0));
+        assert_eq!("2-4".parse::<LandRange>().unwrap(), LandRange(2, 4));
+        assert!("4-2".parse::<LandRange>().is_err());
+    }
+
+    #[test]
+    fn seeded_simulations_are_reproducible() {
+        let lands = [vec![true; 24], vec![false; 36]].concat();
+        let run = |seed| {
+            simulate(
+                &lands,
+                &RULE,
+                4,
+                true,
+                1000,
+                &mut StdRng::seed_from_u64(seed),
+            )
+        };
+        let simulation = run(42);
+        assert_eq!(simulation, run(42));
+        assert_eq!(simulation.kept.values().sum::<u32>(), 1000);
+        // Missing a land drop misses the later ones too
+        assert!(simulation.on_curve.windows(2).all(|w| w[1] <= w[0]));
+        for (turn, drops) in simulation.land_drops.iter().enumerate() {
+            assert_eq!(drops.iter().sum::<u32>(), 1000);
+            // At most one land a turn
+            assert!(drops[turn + 2..].iter().all(|&count| count == 0));
+            assert_eq!(drops[turn + 1], simulation.on_curve[turn]);
+            let average = drops
+                .iter()
+                .zip(0..)
+                .map(|(&count, lands)| f64::from(count * lands))
+                .sum::<f64>()
+                / 1000.0;
+            assert!((average - simulation.lands_played[turn]).abs() < 1e-9);
+        }
+        assert!(simulation
+            .lands_played
+            .windows(2)
+            .all(|w| (0.0..=1.0).contains(&(w[1] - w[0]))));
+    }
+
+    #[test]
+    fn land_counts_bound_the_land_drops() {
+        let run =
+            |lands: &[bool]| simulate(lands, &RULE, 3, false, 100, &mut StdRng::seed_from_u64(7));
+        let all_lands = run(&[true; 40]);
+        assert_eq!(all_lands.on_curve, [100, 100, 100]);
+        assert_eq!(all_lands.land_drops[2], [0, 0, 0, 100]);
+        // Hands without lands are kept once down to 5 cards
+        let no_lands = run(&[false; 40]);
+        assert_eq!(no_lands.kept, BTreeMap::from([(5, 100)]));
+        assert_eq!(no_lands.on_curve, [0, 0, 0]);
+        assert!(no_lands.land_drops.iter().all(|drops| drops[0] == 100));
+    }
+}
@@ -0,0 +1,327 @@
+use crate::{
+    collection::simplified_name,
+    cost_model::{CostModel, DeckCostStrategy},
+    name_index::NameIndex,
+    Rarity, Roster,
+};
+use anyhow::{bail, Context, Result};
+use std::collections::{BTreeSet, HashMap};
+
+/// Deck cost used for every deck when decks are weighted equally
+const EQUAL_DECK_COST: f32 = 100.0;
+
+/// What one deck adds to the score of a card
+#[derive(Debug, Clone)]
+pub struct Contribution<'r> {
+    pub deck: &'r str,
+    pub missing: u8,
+    pub deck_cost: f32,
+    pub tiebreaker_bonus: f32,
+    pub score: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Suggestion<'r> {
+    pub card: &'r String,
+    pub rarity: Rarity,
+    pub score: f32,
+    /// Share of the summed scores of every card, in percent. Unlike the score,
+    /// it does not depend on how many wildcards we own or how many decks are
+    /// in the roster, so it can be compared between runs.
+    pub share: f32,
+    pub contributions: Vec<Contribution<'r>>,
+}
+
+/// Restricts which cards are suggested
+#[derive(Debug, Clone, Copy)]
+pub struct SuggestionFilter<'f> {
+    /// When empty, every rarity is kept
+    pub rarities: &'f [Rarity],
+    pub set: Option<&'f str>,
+    /// Only decks with this tag are taken into account, so shares are then
+    /// relative to those decks
+    pub tag: Option<&'f str>,
+    /// Cards we have decided never to craft, by the name the collection knows
+    /// them by
+    pub never_craft: &'f BTreeSet<String>,
+}
+
+/// Scores every missing card of the roster, by summing the cost of the missing
+/// copies over each deck that plays it, divided by the cost of that deck. When
+/// `strategy` is `None`, decks are all weighted equally. The suggestions come
+/// out best first.
+pub fn suggestions<'r>(
+    roster: &'r Roster,
+    cost_model: &dyn CostModel,
+    ignore_sideboard: bool,
+    strategy: Option<&DeckCostStrategy>,
+    filter: SuggestionFilter,
+) -> Result<Vec<Suggestion<'r>>> {
+    let mut suggestions: HashMap<&String, Suggestion> = HashMap::new();
+    let decks = roster
+        .decks()
+        .filter(|deck| filter.tag.iter().all(|tag| deck.has_tag(tag)));
+    for deck in decks {
+        let deck_cost = match strategy {
+            Some(strategy) => cost_model
+                .deck_cost(deck, ignore_sideboard, strategy)
+                .with_context(|| format!("Failed to compute deck cost for `{}`", deck.name))?,
+            None => EQUAL_DECK_COST,
+        };
+        // Complete decks have nothing left to suggest
+        if deck_cost.abs() < f32::EPSILON {
+            continue;
+        }
+        for (card_name, deck_amount) in deck.cards(cost_model.sideboard(ignore_sideboard)) {
+            let rarity = cost_model
+                .cheapest_rarity(card_name)
+                .context("When computing rarity")?;
+            if matches!(rarity, Rarity::Land | Rarity::Unknown) {
+                continue;
+            }
+            let missing = cost_model.missing_amount(card_name, deck_amount)?;
+            if missing == 0 {
+                continue;
+            }
+            let tiebreaker_bonus = cost_model.tiebreaker_bonus(missing);
+            let score = cost_model.card_cost_considering_deck(card_name, deck_amount)? / deck_cost;
+            let suggestion = suggestions.entry(card_name).or_insert_with(|| Suggestion {
+                card: card_name,
+                rarity,
+                score: 0.0,
+                share: 0.0,
+                contributions: Vec::new(),
+            });
+            suggestion.score += score;
+            suggestion.contributions.push(Contribution {
+                deck: &deck.name,
+                missing,
+                deck_cost,
+                tiebreaker_bonus,
+                score,
+            });
+        }
+    }
+    let total: f32 = suggestions.values().map(|s| s.score).sum();
+    let mut suggestions: Vec<_> = suggestions.into_values().collect();
+    for suggestion in &mut suggestions {
+        suggestion.share = 100.0 * suggestion.score / total;
+        suggestion
+            .contributions
+            .sort_unstable_by(|a, b| b.score.total_cmp(&a.score));
+    }
+    // Filtering cards only after normalizing keeps shares comparable between
+    // rarity, set and never craft filters
+    let mut suggestions: Vec<_> = suggestions
+        .into_iter()
+        .filter(|s| filter.rarities.is_empty() || filter.rarities.contains(&s.rarity))
+        .filter(|s| !filter.never_craft.contains(simplified_name(s.card)))
+        .map(|s| {
+            let in_set = match filter.set {
+                Some(set) => cost_model.cheapest_version(s.card)?.2 == set,
+                None => true,
+            };
+            Ok(in_set.then_some(s))
+        })
+        .filter_map(Result::transpose)
+        .collect::<Result<_>>()?;
+    suggestions.sort_unstable_by(|a, b| b.score.total_cmp(&a.score).then(a.card.cmp(b.card)));
+    Ok(suggestions)
+}
+
+/// The suggestion for a card, ignoring case and punctuation in its name
+pub fn find<'s, 'r>(suggestions: &'s [Suggestion<'r>], card: &str) -> Result<&'s Suggestion<'r>> {
+    let index = NameIndex::new(suggestions.iter().map(|s| s.card));
+    let found = index
+        .exact(card)
+        .and_then(|name| suggestions.iter().find(|s| s.card == name));
+    if let Some(suggestion) = found {
+        return Ok(suggestion);
+    }
+    let close = index.suggestions(card, 3);
+    if close.is_empty() {
+        bail!("`{card}` is not missing from any deck");
+    }
+    bail!(
+        "`{card}` is not missing from any deck. Close matches: {}",
+        close.join(", ")
+    )
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{cost_model::FakeCollection, Deck};
+    use assert_fs::NamedTempFile;
+    use std::collections::BTreeSet;
+
+    /// Cards of the decks of `roster`
+    pub(crate) fn collection() -> FakeCollection {
+        FakeCollection::new(&[
+            ("Mountain", 0, Rarity::Land, "ltr"),
+            ("Lightning Strike", 4, Rarity::Common, "m19"),
+            ("Play with Fire", 1, Rarity::Uncommon, "mid"),
+            ("Fable of the Mirror-Breaker", 0, Rarity::Rare, "neo"),
+            (
+                "Ojer Taq, Deepest Foundation // Temple of Civilization",
+                0,
+                Rarity::Mythic,
+                "lci",
+            ),
+        ])
+    }
+
+    /// Decks of 20 Mountains and the given cards. The roster has to be
+    /// dropped before its file, which the order of the bindings takes care of.
+    pub(crate) fn roster_of(decks: &[(&str, &str)]) -> (NamedTempFile, Roster) {
+        let file = NamedTempFile::new("roster.json").unwrap();
+        std::fs::write(&file, "[]").unwrap();
+        let mut roster = Roster::open(&file).unwrap();
+        for (name, cards) in decks {
+            let deck: Deck = format!("Deck\n20 Mountain\n{cards}").parse().unwrap();
+            roster.add_deck(deck.name(name));
+        }
+        (file, roster)
+    }
+
+    fn roster() -> (NamedTempFile, Roster) {
+        let (file, mut roster) = roster_of(&[
+            ("Burn", "4 Lightning Strike\n4 Play with Fire\n"),
+            ("Fable", "4 Fable of the Mirror-Breaker\n"),
+            ("Both", "2 Play with Fire\n1 Fable of the Mirror-Breaker\n"),
+        ]);
+        roster.decks_mut().last().unwrap().add_tag("brew");
+        (file, roster)
+    }
+
+    fn no_filter(never_craft: &BTreeSet<String>) -> SuggestionFilter<'_> {
+        SuggestionFilter {
+            rarities: &[],
+            set: None,
+            tag: None,
+            never_craft,
+        }
+    }
+
+    #[test]
+    fn scores_add_up_over_decks() -> Result<()> {
+        let (_file, roster) = roster();
+        let never_craft = BTreeSet::new();
+        let ranked = suggestions(
+            &roster,
+            &collection(),
+            false,
+            Some(&DeckCostStrategy::Raw),
+            no_filter(&never_craft),
+        )?;
+        let cards = ranked.iter().map(|s| s.card.as_str()).collect::<Vec<_>>();
+        assert_eq!(cards, ["Fable of the Mirror-Breaker", "Play with Fire"]);
+        let shares: f32 = ranked.iter().map(|s| s.share).sum();
+        assert!((shares - 100.0).abs() < 1e-3);
+
+        // Both misses a Fable worth 4 plus a tiebreaker of 4, out of a deck
+        // cost of 6, and Fable misses 4 Fables worth 16 plus 1 out of 16
+        let fable = find(&ranked, "fable of the mirror-breaker")?;
+        let decks = fable
+            .contributions
+            .iter()
+            .map(|c| (c.deck, c.missing))
+            .collect::<Vec<_>>();
+        assert_eq!(decks, [("Both", 1), ("Fable", 4)]);
+        assert!((fable.contributions[0].tiebreaker_bonus - 4.0).abs() < f32::EPSILON);
+        assert!((fable.contributions[0].score - 8.0 / 6.0).abs() < 1e-6);
+        assert!((fable.contributions[1].score - 17.0 / 16.0).abs() < 1e-6);
+        let summed: f32 = fable.contributions.iter().map(|c| c.score).sum();
+        assert!((fable.score - summed).abs() < 1e-6);
+
+        // Decks weighted equally all cost the same
+        let equally = suggestions(&roster, &collection(), false, None, no_filter(&never_craft))?;
+        let play_with_fire = find(&equally, "Play with Fire")?;
+        assert!(play_with_fire
+            .contributions
+            .iter()
+            .all(|c| (c.deck_cost - EQUAL_DECK_COST).abs() < f32::EPSILON));
+        Ok(())
+    }
+
+    #[test]
+    fn explained_card_must_be_missing() -> Result<()> {
+        let (_file, roster) = roster();
+        let never_craft = BTreeSet::new();
+        let suggestions =
+            suggestions(&roster, &collection(), false, None, no_filter(&never_craft))?;
+        assert_eq!(
+            find(&suggestions, "Play with Fir").unwrap_err().to_string(),
+            "`Play with Fir` is not missing from any deck. Close matches: Play with Fire"
+        );
+        assert_eq!(
+            find(&suggestions, "Lightning Strike")
+                .unwrap_err()
+                .to_string(),
+            "`Lightning Strike` is not missing from any deck"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn filters_combine() -> Result<()> {
+        let (_file, mut roster) = roster();
+        let ojer: Deck = "Deck\n20 Mountain\n1 Ojer Taq, Deepest Foundation // Temple of Civilization\n1 Fable of the Mirror-Breaker\n"
+            .parse()?;
+        let mut ojer = ojer.name("Ojer");
+        ojer.add_tag("brew");
+        roster.add_deck(ojer);
+        let collection = collection();
+        let cards = |filter: SuggestionFilter| -> Result<Vec<(String, f32)>> {
+            Ok(suggestions(&roster, &collection, false, None, filter)?
+                .into_iter()
+                .map(|s| (s.card.clone(), s.share))
+                .collect())
+        };
+        let names = |cards: &[(String, f32)]| -> Vec<String> {
+            cards.iter().map(|(card, _)| card.clone()).collect()
+        };
+
+        // The collection knows double-faced cards by their front face
+        let never_craft = BTreeSet::from(["Ojer Taq, Deepest Foundation".to_owned()]);
+        assert_eq!(
+            names(&cards(no_filter(&never_craft))?),
+            ["Fable of the Mirror-Breaker", "Play with Fire"]
+        );
+
+        let nothing = BTreeSet::new();
+        let brews = cards(SuggestionFilter {
+            tag: Some("brew"),
+            ..no_filter(&nothing)
+        })?;
+        assert_eq!(brews.len(), 3);
+        let rare_brews = cards(SuggestionFilter {
+            rarities: &[Rarity::Rare, Rarity::Mythic],
+            tag: Some("brew"),
+            ..no_filter(&never_craft)
+        })?;
+        assert_eq!(names(&rare_brews), ["Fable of the Mirror-Breaker"]);
+        let fable_share = |cards: &[(String, f32)]| {
+            cards
+                .iter()
+                .find(|(card, _)| card == "Fable of the Mirror-Breaker")
+                .map_or(0.0, |(_, share)| *share)
+        };
+        // Shares are summed in no particular order
+        assert!((fable_share(&rare_brews) - fable_share(&brews)).abs() < 1e-4);
+        assert!(fable_share(&brews) > 0.0);
+
+        let from_mid = cards(SuggestionFilter {
+            set: Some("mid"),
+            ..no_filter(&nothing)
+        })?;
+        assert_eq!(names(&from_mid), ["Play with Fire"]);
+        let none = cards(SuggestionFilter {
+            rarities: &[Rarity::Uncommon],
+            set: Some("neo"),
+            ..no_filter(&nothing)
+        })?;
+        assert!(none.is_empty());
+        Ok(())
+    }
+}
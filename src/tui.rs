@@ -0,0 +1,662 @@
+use crate::{
+    config::Config,
+    cost_model::CostModel,
+    suggestions::{suggestions, SuggestionFilter},
+    Deck, Rarity, Roster,
+};
+use anyhow::{Context, Result};
+use crossterm::{
+    cursor::Show,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    widgets::{Block, Borders, List, ListItem, Paragraph, Row, Table, TableState},
+    Frame, Terminal,
+};
+use std::io;
+
+/// Column the deck list is sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    Cost,
+    Completion,
+}
+
+impl SortKey {
+    fn next(self) -> Self {
+        match self {
+            Self::Name => Self::Cost,
+            Self::Cost => Self::Completion,
+            Self::Completion => Self::Name,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct DeckRow {
+    name: String,
+    /// `None` when the deck plays cards we know nothing of
+    cost: Option<f32>,
+    completion: Option<f32>,
+}
+
+#[derive(Debug)]
+struct MissingCard {
+    name: String,
+    amount: u8,
+    rarity: Rarity,
+    set: String,
+}
+
+#[derive(Debug)]
+struct SuggestedCard {
+    name: String,
+    share: f32,
+    decks: Vec<String>,
+}
+
+/// A decklist being edited, in the same format as `show` prints it
+#[derive(Debug)]
+struct Editor {
+    deck_name: String,
+    lines: Vec<String>,
+    row: usize,
+    /// In characters, not bytes
+    col: usize,
+}
+
+impl Editor {
+    fn new(deck: &Deck) -> Self {
+        Self {
+            deck_name: deck.name.clone(),
+            lines: deck.to_string().lines().map(str::to_owned).collect(),
+            row: 0,
+            col: 0,
+        }
+    }
+
+    fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    fn byte_index(&self) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices()
+            .nth(self.col)
+            .map_or(line.len(), |(i, _)| i)
+    }
+
+    fn line_len(&self) -> usize {
+        self.lines[self.row].chars().count()
+    }
+
+    fn insert(&mut self, c: char) {
+        let i = self.byte_index();
+        self.lines[self.row].insert(i, c);
+        self.col += 1;
+    }
+
+    fn new_line(&mut self) {
+        let i = self.byte_index();
+        let rest = self.lines[self.row].split_off(i);
+        self.row += 1;
+        self.col = 0;
+        self.lines.insert(self.row, rest);
+    }
+
+    fn backspace(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            let i = self.byte_index();
+            self.lines[self.row].remove(i);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_len();
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    fn move_cursor(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Left if self.col > 0 => self.col -= 1,
+            KeyCode::Right if self.col < self.line_len() => self.col += 1,
+            KeyCode::Up if self.row > 0 => self.row -= 1,
+            KeyCode::Down if self.row + 1 < self.lines.len() => self.row += 1,
+            KeyCode::Home => self.col = 0,
+            KeyCode::End => self.col = self.line_len(),
+            _ => {}
+        }
+        self.col = self.col.min(self.line_len());
+    }
+}
+
+/// Share of the cards of a deck that we own, in percent. Basic lands are
+/// always owned.
+fn completion(cost_model: &dyn CostModel, deck: &Deck, ignore_sideboard: bool) -> Result<f32> {
+    let mut total = 0.0;
+    let mut missing = 0.0;
+    for (card_name, amount) in deck.cards(cost_model.sideboard(ignore_sideboard)) {
+        total += f32::from(amount);
+        missing += f32::from(cost_model.missing_amount(card_name, amount)?);
+    }
+    if total == 0.0 {
+        return Ok(100.0);
+    }
+    Ok(100.0 * (total - missing) / total)
+}
+
+/// State of `detr tui`. Everything it shows is computed through the same
+/// `Roster` and `CostModel` as the other commands, so that they agree.
+pub struct App<'a> {
+    roster: &'a mut Roster,
+    cost_model: &'a dyn CostModel,
+    config: &'a Config,
+    ignore_sideboard: bool,
+    rows: Vec<DeckRow>,
+    sort: SortKey,
+    table: TableState,
+    missing: Vec<MissingCard>,
+    suggestions: Vec<SuggestedCard>,
+    editor: Option<Editor>,
+    status: String,
+    quit: bool,
+}
+
+impl<'a> App<'a> {
+    pub fn new(
+        roster: &'a mut Roster,
+        cost_model: &'a dyn CostModel,
+        config: &'a Config,
+        ignore_sideboard: bool,
+    ) -> Self {
+        let mut app = Self {
+            roster,
+            cost_model,
+            config,
+            ignore_sideboard,
+            rows: Vec::new(),
+            sort: SortKey::Cost,
+            table: TableState::default(),
+            missing: Vec::new(),
+            suggestions: Vec::new(),
+            editor: None,
+            status: String::new(),
+            quit: false,
+        };
+        app.refresh();
+        app
+    }
+
+    #[must_use]
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    #[must_use]
+    pub fn selected_deck(&self) -> Option<&str> {
+        let row = self.rows.get(self.table.selected()?)?;
+        Some(&row.name)
+    }
+
+    /// Recomputes everything shown, after the roster changed
+    fn refresh(&mut self) {
+        let selected = self.selected_deck().map(str::to_owned);
+        self.rows = self
+            .roster
+            .decks()
+            .map(|deck| DeckRow {
+                name: deck.name.clone(),
+                cost: self
+                    .cost_model
+                    .deck_cost(deck, self.ignore_sideboard, &self.config.deck_cost)
+                    .ok(),
+                completion: completion(self.cost_model, deck, self.ignore_sideboard).ok(),
+            })
+            .collect();
+        let filter = SuggestionFilter {
+            rarities: &[],
+            set: None,
+            tag: None,
+            never_craft: &self.config.never_craft,
+        };
+        let suggestions = suggestions(
+            self.roster,
+            self.cost_model,
+            self.ignore_sideboard,
+            Some(&self.config.deck_cost),
+            filter,
+        );
+        match suggestions {
+            Ok(suggestions) => {
+                self.suggestions = suggestions
+                    .into_iter()
+                    .map(|s| SuggestedCard {
+                        name: s.card.clone(),
+                        share: s.share,
+                        decks: s.contributions.iter().map(|c| c.deck.to_owned()).collect(),
+                    })
+                    .collect();
+            }
+            Err(err) => {
+                self.suggestions.clear();
+                self.status = format!("Failed to compute suggestions: {err:#}");
+            }
+        }
+        self.sort_rows();
+        let position = selected.and_then(|name| self.rows.iter().position(|r| r.name == name));
+        self.select(position.or((!self.rows.is_empty()).then_some(0)));
+    }
+
+    fn sort_rows(&mut self) {
+        match self.sort {
+            SortKey::Name => self.rows.sort_by(|a, b| a.name.cmp(&b.name)),
+            SortKey::Cost => self.rows.sort_by(|a, b| {
+                let (a, b) = (a.cost.unwrap_or(f32::MAX), b.cost.unwrap_or(f32::MAX));
+                a.total_cmp(&b)
+            }),
+            SortKey::Completion => self.rows.sort_by(|a, b| {
+                let (a, b) = (a.completion.unwrap_or(0.0), b.completion.unwrap_or(0.0));
+                b.total_cmp(&a)
+            }),
+        }
+    }
+
+    fn select(&mut self, position: Option<usize>) {
+        self.table.select(position);
+        self.missing.clear();
+        let Some(deck) = self
+            .selected_deck()
+            .and_then(|name| self.roster.find(name).ok())
+        else {
+            return;
+        };
+        match self.cost_model.missing(deck, self.ignore_sideboard) {
+            Ok(missing) => {
+                self.missing = missing
+                    .into_iter()
+                    .map(|(name, amount, rarity, set)| MissingCard {
+                        name: name.clone(),
+                        amount,
+                        rarity,
+                        set: set.clone(),
+                    })
+                    .collect();
+                self.missing
+                    .sort_by(|a, b| b.rarity.cmp(&a.rarity).then(a.name.cmp(&b.name)));
+            }
+            Err(err) => self.status = format!("{err:#}"),
+        }
+    }
+
+    fn move_selection(&mut self, offset: isize) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let current = self.table.selected().unwrap_or(0);
+        let position = current
+            .saturating_add_signed(offset)
+            .min(self.rows.len() - 1);
+        self.select(Some(position));
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        if self.editor.is_some() {
+            self.handle_editor_key(key);
+            return;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Char('s') => {
+                self.sort = self.sort.next();
+                let selected = self.selected_deck().map(str::to_owned);
+                self.sort_rows();
+                let position =
+                    selected.and_then(|name| self.rows.iter().position(|r| r.name == name));
+                self.table.select(position);
+            }
+            KeyCode::Char('e') => {
+                if let Some(deck) = self.selected_deck().and_then(|n| self.roster.find(n).ok()) {
+                    self.editor = Some(Editor::new(deck));
+                    self.status.clear();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_editor_key(&mut self, key: KeyEvent) {
+        let Some(editor) = self.editor.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => {
+                self.editor = None;
+                "Edit cancelled".clone_into(&mut self.status);
+            }
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                match self.save_editor() {
+                    Ok(deck_name) => {
+                        self.editor = None;
+                        self.status = format!("Saved `{deck_name}`");
+                        self.refresh();
+                    }
+                    Err(err) => self.status = format!("{err:#}"),
+                }
+            }
+            // Shortcuts we do not know of are not text
+            KeyCode::Char(_)
+                if key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {}
+            KeyCode::Char(c) => editor.insert(c),
+            KeyCode::Enter => editor.new_line(),
+            KeyCode::Backspace => editor.backspace(),
+            code => editor.move_cursor(code),
+        }
+    }
+
+    /// Replaces the edited deck in the roster, as long as it parses and we know
+    /// every card it plays
+    fn save_editor(&mut self) -> Result<String> {
+        let editor = self.editor.as_ref().context("No deck is being edited")?;
+        let deck_name = editor.deck_name.clone();
+        let previous = self.roster.find(&deck_name)?;
+        let deck = editor
+            .text()
+            .parse::<Deck>()
+            .context("Failed to parse decklist")?
+            .name(&deck_name)
+            .with_metadata_of(previous);
+        for card_name in deck.card_names() {
+            self.cost_model
+                .prints(card_name)
+                .with_context(|| format!("Unknown card `{card_name}`"))?;
+        }
+        self.roster.replace(&deck_name, deck)?;
+        Ok(deck_name)
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] = *Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(frame.size())
+        else {
+            unreachable!()
+        };
+        let [decks, details] = *Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(main)
+        else {
+            unreachable!()
+        };
+        self.draw_decks(frame, decks);
+        if let Some(editor) = &self.editor {
+            draw_editor(frame, editor, details);
+        } else {
+            self.draw_details(frame, details);
+        }
+        let help = if self.editor.is_some() {
+            "Ctrl-S save, Esc cancel"
+        } else {
+            "q quit, j/k select, s sort, e edit"
+        };
+        let status_line = if self.status.is_empty() {
+            help.to_owned()
+        } else {
+            format!("{} | {help}", self.status)
+        };
+        frame.render_widget(Paragraph::new(status_line), status);
+    }
+
+    fn draw_decks(&mut self, frame: &mut Frame, area: Rect) {
+        let format_value = |value: Option<f32>| value.map_or("?".to_owned(), |v| format!("{v:.2}"));
+        let rows = self.rows.iter().map(|row| {
+            Row::new(vec![
+                row.name.clone(),
+                format_value(row.cost),
+                format_value(row.completion),
+            ])
+        });
+        let widths = [
+            Constraint::Min(10),
+            Constraint::Length(8),
+            Constraint::Length(8),
+        ];
+        let title = match self.sort {
+            SortKey::Name => "Decks, by name",
+            SortKey::Cost => "Decks, by cost",
+            SortKey::Completion => "Decks, by completion",
+        };
+        let table = Table::new(rows)
+            .header(
+                Row::new(vec!["Name", "Cost", "Owned %"])
+                    .style(Style::default().add_modifier(Modifier::BOLD)),
+            )
+            .widths(&widths)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, area, &mut self.table);
+    }
+
+    fn draw_details(&self, frame: &mut Frame, area: Rect) {
+        let [missing, suggested] = *Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+            .split(area)
+        else {
+            unreachable!()
+        };
+        let missing_items = self
+            .missing
+            .iter()
+            .map(|card| {
+                let rarity = format!("{:?}", card.rarity);
+                ListItem::new(format!(
+                    "{rarity:<9}{} {} ({})",
+                    card.amount, card.name, card.set
+                ))
+            })
+            .collect::<Vec<_>>();
+        let missing_list =
+            List::new(missing_items).block(Block::default().borders(Borders::ALL).title("Missing"));
+        frame.render_widget(missing_list, missing);
+
+        let selected = self.selected_deck();
+        let suggested_items = self
+            .suggestions
+            .iter()
+            .filter(|s| selected.is_some_and(|deck| s.decks.iter().any(|d| d == deck)))
+            .map(|s| ListItem::new(format!("{:5.2}% {}", s.share, s.name)))
+            .collect::<Vec<_>>();
+        let suggested_list = List::new(suggested_items)
+            .block(Block::default().borders(Borders::ALL).title("Suggestions"));
+        frame.render_widget(suggested_list, suggested);
+    }
+}
+
+fn draw_editor(frame: &mut Frame, editor: &Editor, area: Rect) {
+    let title = format!("Editing {}", editor.deck_name);
+    let paragraph =
+        Paragraph::new(editor.text()).block(Block::default().borders(Borders::ALL).title(title));
+    frame.render_widget(paragraph, area);
+    let x = u16::try_from(editor.col).unwrap_or(u16::MAX);
+    let y = u16::try_from(editor.row).unwrap_or(u16::MAX);
+    frame.set_cursor(
+        area.x.saturating_add(1).saturating_add(x),
+        area.y.saturating_add(1).saturating_add(y),
+    );
+}
+
+/// Draws the app and feeds it key presses until it quits
+pub fn event_loop<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
+    while !app.should_quit() {
+        terminal.draw(|frame| app.draw(frame))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                app.handle_key(key);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs the app in the alternate screen of the terminal, restoring the
+/// terminal even if the app fails
+pub fn run(app: &mut App) -> Result<()> {
+    enable_raw_mode()?;
+    let result = execute!(io::stdout(), EnterAlternateScreen)
+        .and_then(|()| Terminal::new(CrosstermBackend::new(io::stdout())))
+        .map_err(anyhow::Error::from)
+        .and_then(|mut terminal| event_loop(&mut terminal, app));
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, Show)?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cost_model::DeckCostStrategy,
+        suggestions::tests::{collection, roster_of},
+        Sideboard,
+    };
+    use assert_fs::NamedTempFile;
+    use ratatui::backend::TestBackend;
+
+    fn roster() -> (NamedTempFile, Roster) {
+        roster_of(&[
+            ("Burn", "4 Lightning Strike\n4 Play with Fire\n"),
+            ("Fable", "4 Fable of the Mirror-Breaker\n"),
+        ])
+    }
+
+    fn config() -> Config {
+        Config {
+            deck_cost: DeckCostStrategy::Raw,
+            ..Config::default()
+        }
+    }
+
+    fn render(app: &mut App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(200, 20)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(usize::from(buffer.area.width))
+            .map(|line| {
+                line.iter()
+                    .map(|cell| cell.symbol.as_str())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn shows_missing_cards_of_selected_deck() {
+        let (_file, mut roster) = roster();
+        let collection = collection();
+        let config = config();
+        let mut app = App::new(&mut roster, &collection, &config, false);
+
+        // Burn only misses 3 Play with Fire, so it is the cheapest
+        assert_eq!(app.selected_deck(), Some("Burn"));
+        let screen = render(&mut app);
+        assert!(screen.contains("Uncommon 3 Play with Fire (mid)"));
+        assert!(!screen.contains("Fable of the Mirror-Breaker"));
+
+        press(&mut app, KeyCode::Char('j'));
+        assert_eq!(app.selected_deck(), Some("Fable"));
+        let screen = render(&mut app);
+        assert!(screen.contains("Rare     4 Fable of the Mirror-Breaker (neo)"));
+    }
+
+    #[test]
+    fn sorting_keeps_selection() {
+        let (_file, mut roster) = roster();
+        let collection = collection();
+        let config = config();
+        let mut app = App::new(&mut roster, &collection, &config, false);
+
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('s'));
+        assert_eq!(app.sort, SortKey::Completion);
+        assert_eq!(app.selected_deck(), Some("Fable"));
+        assert_eq!(app.rows[0].name, "Burn");
+        assert!(render(&mut app).contains("Decks, by completion"));
+    }
+
+    #[test]
+    fn editor_replaces_deck() {
+        let (_file, mut roster) = roster();
+        let collection = collection();
+        let config = config();
+        let mut app = App::new(&mut roster, &collection, &config, false);
+
+        press(&mut app, KeyCode::Char('e'));
+        // Turn `20 Mountain` into `24 Mountain`
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Backspace);
+        press(&mut app, KeyCode::Char('4'));
+        app.handle_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL));
+        assert_eq!(app.status, "Saved `Burn`");
+        drop(app);
+        let burn = roster.find("Burn").unwrap();
+        assert!(burn
+            .cards(Sideboard::Counted)
+            .any(|(name, amount)| name == "Mountain" && amount == 24));
+    }
+
+    #[test]
+    fn editor_refuses_unknown_cards() {
+        let (_file, mut roster) = roster();
+        let collection = collection();
+        let config = config();
+        let mut app = App::new(&mut roster, &collection, &config, false);
+
+        press(&mut app, KeyCode::Char('e'));
+        press(&mut app, KeyCode::End);
+        press(&mut app, KeyCode::Enter);
+        for c in "1 Shock".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        app.handle_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL));
+        assert!(app.status.contains("Unknown card `Shock`"));
+        assert!(app.editor.is_some());
+    }
+
+    #[test]
+    fn editor_ignores_shortcuts() {
+        let (_file, mut roster) = roster();
+        let collection = collection();
+        let config = config();
+        let mut app = App::new(&mut roster, &collection, &config, false);
+
+        press(&mut app, KeyCode::Char('e'));
+        let before = app.editor.as_ref().unwrap().text();
+        app.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::ALT));
+        assert_eq!(app.editor.as_ref().unwrap().text(), before);
+        app.handle_key(KeyEvent::new(KeyCode::Char('D'), KeyModifiers::SHIFT));
+        assert_eq!(app.editor.as_ref().unwrap().text(), format!("D{before}"));
+    }
+}
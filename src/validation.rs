@@ -0,0 +1,474 @@
+use crate::{
+    analysis::CardType, mtga_id_translator::NetCardData, CardVersion, Deck, Sideboard, Zone,
+};
+use itertools::Itertools;
+use std::{collections::HashMap, fmt};
+
+pub const MIN_MAIN_SIZE: u32 = 60;
+pub const MAX_SIDEBOARD_SIZE: u32 = 15;
+const MAX_COPIES: u32 = 4;
+
+const BASIC_LANDS: [&str; 6] = ["Plains", "Island", "Swamp", "Mountain", "Forest", "Wastes"];
+
+/// Cards a deck may have any number of
+const ANY_NUMBER: [&str; 10] = [
+    "Relentless Rats",
+    "Persistent Petitioners",
+    "Rat Colony",
+    "Shadowborn Apostle",
+    "Dragon's Approach",
+    "Slime Against Humanity",
+    "Hare Apparent",
+    "Templar Knight",
+    "Tempest Hawk",
+    "Cid, Timeless Artificer",
+];
+
+/// Cards a deck may have a given number of, instead of four
+const OWN_LIMIT: [(&str, u32); 2] = [("Seven Dwarves", 7), ("Nazgûl", 9)];
+
+const COMPANIONS: [&str; 10] = [
+    "Gyruda, Doom of Depths",
+    "Jegantha, the Wellspring",
+    "Kaheera, the Orphanguard",
+    "Keruga, the Macrosage",
+    "Lurrus of the Dream-Den",
+    "Lutri, the Spellchaser",
+    "Obosh, the Preypiercer",
+    "Umori, the Collector",
+    "Yorion, Sky Nomad",
+    "Zirda, the Dawnwaker",
+];
+
+/// Creature types Kaheera, the Orphanguard allows
+const KAHEERA_TYPES: [&str; 5] = ["Cat", "Elemental", "Nightmare", "Dinosaur", "Beast"];
+
+const CARD_TYPES: [&str; 9] = [
+    "Artifact",
+    "Battle",
+    "Creature",
+    "Enchantment",
+    "Instant",
+    "Kindred",
+    "Land",
+    "Planeswalker",
+    "Sorcery",
+];
+
+/// Keyword abilities that are activated, and so have no colon in their text
+const ACTIVATED_KEYWORDS: [&str; 14] = [
+    "Boast",
+    "Craft",
+    "Crew",
+    "Embalm",
+    "Equip",
+    "Eternalize",
+    "Fortify",
+    "Level up",
+    "Ninjutsu",
+    "Outlast",
+    "Reconfigure",
+    "Scavenge",
+    "Transmute",
+    "Unearth",
+];
+
+/// How many copies of a card a deck may have, if it is limited
+fn copy_limit(card_name: &str) -> Option<u32> {
+    let card_name = CardVersion::original_name(card_name);
+    let basic = card_name.strip_prefix("Snow-Covered ").unwrap_or(card_name);
+    if BASIC_LANDS.contains(&basic) || ANY_NUMBER.contains(&card_name) {
+        return None;
+    }
+    let limit = OWN_LIMIT
+        .iter()
+        .find(|(name, _)| *name == card_name)
+        .map_or(MAX_COPIES, |(_, limit)| *limit);
+    Some(limit)
+}
+
+/// Why a deck is not legal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    MainTooSmall(u32),
+    /// Counting the companion and the wishboard
+    SideboardTooLarge(u32),
+    TooManyCopies {
+        card: String,
+        amount: u32,
+        limit: u32,
+    },
+    NotACompanion(String),
+    CompanionCondition {
+        companion: String,
+        condition: &'static str,
+    },
+    /// Cards of the main deck have no card data to check the condition with
+    CompanionUnchecked {
+        companion: String,
+        condition: &'static str,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MainTooSmall(size) => write!(
+                f,
+                "The main deck has {size} cards, at least {MIN_MAIN_SIZE} are needed"
+            ),
+            Self::SideboardTooLarge(size) => write!(
+                f,
+                "The sideboard has {size} cards, at most {MAX_SIDEBOARD_SIZE} are allowed"
+            ),
+            Self::TooManyCopies {
+                card,
+                amount,
+                limit,
+            } => write!(
+                f,
+                "{amount} copies of `{card}`, at most {limit} are allowed"
+            ),
+            Self::NotACompanion(card) => write!(f, "`{card}` is not a companion"),
+            Self::CompanionCondition {
+                companion,
+                condition,
+            } => write!(f, "`{companion}` needs {condition}"),
+            Self::CompanionUnchecked {
+                companion,
+                condition,
+            } => write!(
+                f,
+                "`{companion}` needs {condition}, which was not checked for lack of card data"
+            ),
+        }
+    }
+}
+
+/// Mana value of the card, which is a whole number for every card but a few
+/// Un-cards
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn mana_value(card: &NetCardData) -> u32 {
+    card.cmc as u32
+}
+
+fn is_land(card: &NetCardData) -> bool {
+    CardType::of(&card.type_line) == CardType::Land
+}
+
+fn is_permanent(card: &NetCardData) -> bool {
+    !matches!(
+        CardType::of(&card.type_line),
+        CardType::Instant | CardType::Sorcery | CardType::Other
+    )
+}
+
+/// Card types and subtypes of the front face
+fn front_types(card: &NetCardData) -> (&str, &str) {
+    let front = card.type_line.split(" // ").next().unwrap_or_default();
+    front.split_once(" — ").unwrap_or((front, ""))
+}
+
+fn has_activated_ability(card: &NetCardData) -> bool {
+    card.oracle_text.lines().any(|line| {
+        line.contains(':')
+            || line.contains("cycling")
+            || ACTIVATED_KEYWORDS
+                .iter()
+                .any(|keyword| line.starts_with(keyword))
+    })
+}
+
+/// What a companion asks of the main deck, and whether the deck does it, or
+/// `None` if cards of the main deck have no card data
+fn companion_condition(
+    companion: &str,
+    deck: &Deck,
+    main_size: u32,
+    card_data: &HashMap<String, NetCardData>,
+) -> (&'static str, Option<bool>) {
+    let main = deck
+        .cards_by_zone()
+        .filter(|(zone, _, _)| *zone == Zone::Main)
+        .map(|(_, name, amount)| Some((card_data.get(name)?, u32::from(amount))))
+        .collect::<Option<Vec<_>>>();
+    let all = |condition, holds: fn(&NetCardData) -> bool| {
+        let holds = main
+            .as_ref()
+            .map(|cards| cards.iter().all(|(card, _)| holds(card)));
+        (condition, holds)
+    };
+    match companion {
+        "Yorion, Sky Nomad" => (
+            "a main deck of at least 80 cards",
+            Some(main_size >= MIN_MAIN_SIZE + 20),
+        ),
+        "Gyruda, Doom of Depths" => all("nonland cards of even mana value", |card| {
+            is_land(card) || mana_value(card).is_multiple_of(2)
+        }),
+        "Obosh, the Preypiercer" => all("nonland cards of odd mana value", |card| {
+            is_land(card) || !mana_value(card).is_multiple_of(2)
+        }),
+        "Keruga, the Macrosage" => all("nonland cards of mana value 3 or more", |card| {
+            is_land(card) || mana_value(card) >= 3
+        }),
+        "Lurrus of the Dream-Den" => all("permanent cards of mana value 2 or less", |card| {
+            !is_permanent(card) || mana_value(card) <= 2
+        }),
+        "Zirda, the Dawnwaker" => all("permanent cards with an activated ability", |card| {
+            !is_permanent(card) || has_activated_ability(card)
+        }),
+        "Kaheera, the Orphanguard" => all(
+            "creature cards that are Cats, Elementals, Nightmares, Dinosaurs or Beasts",
+            |card| {
+                let (types, subtypes) = front_types(card);
+                !types.contains("Creature")
+                    || card.oracle_text.contains("Changeling")
+                    || subtypes
+                        .split_whitespace()
+                        .any(|subtype| KAHEERA_TYPES.contains(&subtype))
+            },
+        ),
+        "Jegantha, the Wellspring" => all(
+            "no card with more than one of a mana symbol in its cost",
+            |card| {
+                card.mana_cost
+                    .split(" // ")
+                    .all(|cost| cost.split_inclusive('}').all_unique())
+            },
+        ),
+        "Lutri, the Spellchaser" => {
+            let holds = main.as_ref().map(|cards| {
+                cards
+                    .iter()
+                    .filter(|(card, _)| !is_land(card))
+                    .map(|(card, amount)| (CardVersion::original_name(&card.name), *amount))
+                    .into_grouping_map()
+                    .sum()
+                    .into_values()
+                    .all(|amount| amount == 1)
+            });
+            ("a single copy of each nonland card", holds)
+        }
+        "Umori, the Collector" => {
+            let holds = main.as_ref().map(|cards| {
+                let nonland = cards
+                    .iter()
+                    .filter(|(card, _)| !is_land(card))
+                    .map(|(card, _)| front_types(card).0)
+                    .collect_vec();
+                nonland.is_empty()
+                    || CARD_TYPES.iter().any(|card_type| {
+                        nonland
+                            .iter()
+                            .all(|types| types.split_whitespace().contains(card_type))
+                    })
+            });
+            ("nonland cards that share a card type", holds)
+        }
+        _ => ("", Some(true)),
+    }
+}
+
+/// Checks the construction rules of constructed formats. Companion conditions
+/// are checked with the card data of the cards of the deck.
+#[must_use]
+#[allow(clippy::implicit_hasher)]
+pub fn validate(deck: &Deck, card_data: &HashMap<String, NetCardData>) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let size = |zones: &[Zone]| -> u32 {
+        deck.cards_by_zone()
+            .filter(|(zone, _, _)| zones.contains(zone))
+            .map(|(_, _, amount)| u32::from(amount))
+            .sum()
+    };
+    let main_size = size(&[Zone::Main]);
+    if main_size < MIN_MAIN_SIZE {
+        problems.push(Problem::MainTooSmall(main_size));
+    }
+    // The companion is part of the sideboard, even when it is not listed there
+    let companion_listed = deck
+        .cards_by_zone()
+        .any(|(zone, name, _)| zone != Zone::Companion && Some(name.as_str()) == deck.companion());
+    let side_size = if companion_listed {
+        size(&[Zone::Side, Zone::Wish])
+    } else {
+        size(&[Zone::Companion, Zone::Side, Zone::Wish])
+    };
+    if side_size > MAX_SIDEBOARD_SIZE {
+        problems.push(Problem::SideboardTooLarge(side_size));
+    }
+
+    let mut copies: HashMap<&str, u32> = HashMap::new();
+    for (card_name, amount) in deck.cards(Sideboard::Counted) {
+        *copies
+            .entry(CardVersion::original_name(card_name))
+            .or_default() += u32::from(amount);
+    }
+    let mut too_many = copies
+        .into_iter()
+        .filter_map(|(card, amount)| {
+            let limit = copy_limit(card)?;
+            (amount > limit).then(|| Problem::TooManyCopies {
+                card: card.to_owned(),
+                amount,
+                limit,
+            })
+        })
+        .collect::<Vec<_>>();
+    too_many.sort_by_key(ToString::to_string);
+    problems.extend(too_many);
+
+    if let Some(companion) = deck.companion() {
+        let original = CardVersion::original_name(companion);
+        if COMPANIONS.contains(&original) {
+            match companion_condition(original, deck, main_size, card_data) {
+                (_, Some(true)) => {}
+                (condition, Some(false)) => problems.push(Problem::CompanionCondition {
+                    companion: companion.to_owned(),
+                    condition,
+                }),
+                (condition, None) => problems.push(Problem::CompanionUnchecked {
+                    companion: companion.to_owned(),
+                    condition,
+                }),
+            }
+        } else {
+            problems.push(Problem::NotACompanion(companion.to_owned()));
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rarity;
+
+    #[test]
+    fn copy_limits() {
+        let deck: Deck = "Deck\n24 Snow-Covered Mountain\n12 Relentless Rats\n4 Shock\n4 A-Shock\n16 Mountain\n\nSideboard\n15 Swamp\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            validate(&deck, &HashMap::new()),
+            vec![Problem::TooManyCopies {
+                card: "Shock".to_owned(),
+                amount: 8,
+                limit: 4
+            }]
+        );
+    }
+
+    #[test]
+    fn sizes_and_companion() {
+        let deck: Deck =
+            "Companion\n1 Yorion, Sky Nomad\n\nDeck\n59 Plains\n\nSideboard\n15 Island\n"
+                .parse()
+                .unwrap();
+        let problems = validate(&deck, &HashMap::new());
+        assert_eq!(
+            problems[..2],
+            [Problem::MainTooSmall(59), Problem::SideboardTooLarge(16)]
+        );
+        assert!(matches!(problems[2], Problem::CompanionCondition { .. }));
+
+        let deck: Deck = "Companion\n1 Shock\n\nDeck\n60 Plains\n\nSideboard\n1 Shock\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            validate(&deck, &HashMap::new()),
+            vec![Problem::NotACompanion("Shock".to_owned())]
+        );
+    }
+
+    fn card(name: &str, mana_cost: &str, type_line: &str, oracle_text: &str) -> NetCardData {
+        NetCardData {
+            name: name.to_owned(),
+            rarity: Rarity::Common,
+            set: String::new(),
+            mana_cost: mana_cost.to_owned(),
+            // Each symbol is worth one mana in the costs used here
+            cmc: f32::from(u8::try_from(mana_cost.matches('{').count()).unwrap()),
+            colors: Vec::new(),
+            color_identity: Vec::new(),
+            type_line: type_line.to_owned(),
+            oracle_text: oracle_text.to_owned(),
+            arena_id: None,
+        }
+    }
+
+    #[test]
+    fn companion_conditions() {
+        let card_data = HashMap::from(
+            [
+                card("Mountain", "", "Basic Land — Mountain", "({T}: Add {R}.)"),
+                card("Shock", "{R}", "Instant", ""),
+                card(
+                    "Kari Zev",
+                    "{1}{R}",
+                    "Legendary Creature — Human Warrior",
+                    "",
+                ),
+                card("Goblin Guide", "{R}", "Creature — Goblin Scout", "Haste"),
+                card(
+                    "Embercleave",
+                    "{4}{R}{R}",
+                    "Artifact — Equipment",
+                    "Equip {3}",
+                ),
+                card(
+                    "Mutable Explorer",
+                    "{2}{G}",
+                    "Creature — Shapeshifter",
+                    "Changeling",
+                ),
+                card("Jackal Pup", "{R}", "Creature — Jackal", ""),
+            ]
+            .map(|data| (data.name.clone(), data)),
+        );
+        let problem = |companion: &str, cards: &str| {
+            let deck: Deck = format!("Companion\n1 {companion}\n\nDeck\n40 Mountain\n{cards}")
+                .parse()
+                .unwrap();
+            // Copy limits are left out, to keep the decks short
+            validate(&deck, &card_data).into_iter().find(|problem| {
+                matches!(
+                    problem,
+                    Problem::CompanionCondition { .. } | Problem::CompanionUnchecked { .. }
+                )
+            })
+        };
+        let lurrus = "Lurrus of the Dream-Den";
+        assert_eq!(problem(lurrus, "12 Kari Zev\n8 Shock\n"), None);
+        assert!(matches!(
+            problem(lurrus, "16 Kari Zev\n4 Embercleave\n"),
+            Some(Problem::CompanionCondition { .. })
+        ));
+        let jegantha = "Jegantha, the Wellspring";
+        assert_eq!(problem(jegantha, "20 Kari Zev\n"), None);
+        assert!(problem(jegantha, "16 Kari Zev\n4 Embercleave\n").is_some());
+        let obosh = "Obosh, the Preypiercer";
+        assert_eq!(problem(obosh, "20 Shock\n"), None);
+        assert!(problem(obosh, "16 Shock\n4 Kari Zev\n").is_some());
+        let zirda = "Zirda, the Dawnwaker";
+        assert_eq!(problem(zirda, "20 Embercleave\n"), None);
+        assert!(problem(zirda, "16 Embercleave\n4 Goblin Guide\n").is_some());
+        let kaheera = "Kaheera, the Orphanguard";
+        assert_eq!(problem(kaheera, "16 Mutable Explorer\n4 Shock\n"), None);
+        assert!(problem(kaheera, "20 Jackal Pup\n").is_some());
+        let umori = "Umori, the Collector";
+        assert_eq!(problem(umori, "12 Kari Zev\n8 Jackal Pup\n"), None);
+        assert!(problem(umori, "16 Kari Zev\n4 Shock\n").is_some());
+        let lutri = "Lutri, the Spellchaser";
+        assert!(problem(lutri, "16 Kari Zev\n4 Shock\n").is_some());
+
+        assert_eq!(
+            problem("Keruga, the Macrosage", "20 Lightning Bolt\n"),
+            Some(Problem::CompanionUnchecked {
+                companion: "Keruga, the Macrosage".to_owned(),
+                condition: "nonland cards of mana value 3 or more",
+            })
+        );
+    }
+}
@@ -0,0 +1,49 @@
+use anyhow::Result;
+use detr::{Deck, Sideboard, WishCards, Zone};
+use std::collections::HashMap;
+
+fn cards(deck: &Deck, ignore_sideboard: bool) -> HashMap<String, u8> {
+    deck.cards(Sideboard::new(ignore_sideboard, &WishCards::default()))
+        .map(|(name, amount)| (name.clone(), amount))
+        .collect()
+}
+
+#[test]
+fn companion_is_counted_once() -> Result<()> {
+    // Arena exports list the companion in the sideboard too
+    let arena: Deck =
+        "Companion\n1 Lurrus of the Dream-Den\n\nDeck\n24 Plains\n\nSideboard\n1 Lurrus of the Dream-Den\n2 Rest in Peace\n"
+            .parse()?;
+    assert_eq!(cards(&arena, false)["Lurrus of the Dream-Den"], 1);
+    assert_eq!(cards(&arena, true)["Lurrus of the Dream-Den"], 1);
+    assert!(!cards(&arena, true).contains_key("Rest in Peace"));
+
+    let companion_only: Deck =
+        "Companion\n1 Lurrus of the Dream-Den\n\nDeck\n24 Plains\n".parse()?;
+    assert_eq!(cards(&companion_only, false)["Lurrus of the Dream-Den"], 1);
+    assert!(companion_only.contains(
+        &"Lurrus of the Dream-Den",
+        Sideboard::Ignored(&WishCards::default())
+    ));
+    Ok(())
+}
+
+#[test]
+fn zones() -> Result<()> {
+    let deck: Deck =
+        "Companion\n1 Lurrus of the Dream-Den\n\nDeck\n24 Plains\n\nSideboard\n2 Rest in Peace\n"
+            .parse()?;
+    let zones = deck
+        .cards_by_zone()
+        .map(|(zone, name, amount)| (zone, name.as_str(), amount))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        zones,
+        vec![
+            (Zone::Companion, "Lurrus of the Dream-Den", 1),
+            (Zone::Main, "Plains", 24),
+            (Zone::Side, "Rest in Peace", 2),
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,169 @@
+use anyhow::Result;
+use assert_fs::NamedTempFile;
+use detr::{
+    match_history::{self, GameResult},
+    player_log::{ArenaDeck, ArenaGame, PlayerLog},
+    Deck, Roster, SyncOutcome,
+};
+use std::{collections::HashMap, fs};
+
+#[test]
+fn legacy_log_has_cards_and_inventory() -> Result<()> {
+    let log = PlayerLog::from_file("tests/fixtures/Player-legacy.log")?;
+    // The last collection of the log wins
+    let expected = HashMap::from([(67330, 4), (69108, 2), (70141, 2), (71002, 1)]);
+    assert_eq!(log.cards, Some(expected));
+    let inventory = log.inventory.unwrap();
+    assert_eq!((inventory.gold, inventory.gems), (3150, 400));
+    assert!((inventory.wildcards.rare - 5.0).abs() < f32::EPSILON);
+    assert!((inventory.wildcards.vault_progress - 42.3).abs() < 1e-4);
+    assert_eq!(
+        log.decks,
+        vec![ArenaDeck {
+            id: "3c9e2a71-5f04-4b8d-a1e6-7d20c4f9b835".to_owned(),
+            name: "Burn".to_owned(),
+            companion: None,
+            main: vec![(67330, 20), (69108, 4)],
+            side: vec![(70141, 2)],
+        }]
+    );
+    Ok(())
+}
+
+#[test]
+fn malformed_replies_are_skipped() -> Result<()> {
+    let log = "<== PlayerInventory.GetPlayerCardsV3(41)\n\
+        {\"id\":41,\"payload\":{\"67330\":4}}\n\
+        <== PlayerInventory.GetPlayerCardsV3(42)\n\
+        {\"id\":42,\"payload\":{\"67330\":\"four\"}}\n";
+    let log = PlayerLog::parse(log)?;
+    assert_eq!(log.cards, Some(HashMap::from([(67330, 4)])));
+    assert_eq!(log.skipped.len(), 1);
+    assert!(log.skipped[0].contains("card collection"));
+    Ok(())
+}
+
+#[test]
+fn start_hook_has_inventory_and_decks() -> Result<()> {
+    let log = PlayerLog::from_file("tests/fixtures/Player-starthook.log")?;
+    assert!(log.cards.is_none());
+    let inventory = log.inventory.unwrap();
+    assert_eq!((inventory.gold, inventory.gems), (8750, 1220));
+    assert!((inventory.wildcards.common - 41.0).abs() < f32::EPSILON);
+    assert!((inventory.wildcards.uncommon - 30.0).abs() < f32::EPSILON);
+    assert!((inventory.wildcards.mythic - 3.0).abs() < f32::EPSILON);
+    assert!((inventory.wildcards.vault_progress - 62.7).abs() < 1e-4);
+    assert_eq!(log.decks.len(), 2);
+    assert_eq!(log.decks[0].name, "Mono Red");
+    assert_eq!(log.decks[0].main, vec![(67330, 20), (69108, 4)]);
+    assert_eq!(log.decks[1].companion, Some(71002));
+    Ok(())
+}
+
+fn arena_deck(name: &str, id: &str, main: Vec<(u8, String)>) -> Deck {
+    Deck::from_parts(name, None, main, Vec::new(), Some(id.to_owned()))
+}
+
+#[test]
+fn sync_links_and_updates_decks() -> Result<()> {
+    let roster_file = NamedTempFile::new("test_roster.json")?;
+    fs::write(&roster_file, "[]")?;
+    let mut roster = Roster::open(&roster_file)?;
+    let mut burn: Deck = "Deck\n20 Mountain\n4 Shock\n".parse()?;
+    burn = burn.name("Burn");
+    burn.add_tag("standard");
+    roster.add_deck(burn);
+
+    let mountains = vec![(20, "Mountain".to_owned())];
+    let outcome = roster.sync(arena_deck("Burn", "burn-id", mountains.clone()));
+    assert_eq!(outcome, SyncOutcome::Linked);
+    // Renaming the deck locally keeps it linked, with its tags
+    roster.find_mut("Burn")?.name = "My burn".to_owned();
+    let shocks = vec![(20, "Mountain".to_owned()), (4, "Shock".to_owned())];
+    let outcome = roster.sync(arena_deck("Burn", "burn-id", shocks.clone()));
+    assert_eq!(outcome, SyncOutcome::Updated);
+    let outcome = roster.sync(arena_deck("Burn", "burn-id", shocks));
+    assert_eq!(outcome, SyncOutcome::Unchanged);
+    let deck = roster.find("My burn")?;
+    assert!(deck.has_tag("standard"));
+    assert_eq!(deck.arena_id(), Some("burn-id"));
+
+    let outcome = roster.sync(arena_deck("Burn", "other-id", mountains.clone()));
+    assert_eq!(outcome, SyncOutcome::Added);
+    assert_eq!(roster.len(), 2);
+
+    // The name is now taken by a deck linked to another Arena deck
+    let outcome = roster.sync(arena_deck("Burn", "third-id", mountains));
+    assert_eq!(outcome, SyncOutcome::Added);
+    assert_eq!(roster.find("Burn (2)")?.arena_id(), Some("third-id"));
+    assert_eq!(roster.find("Burn")?.arena_id(), Some("other-id"));
+    Ok(())
+}
+
+#[test]
+fn match_log_has_games() -> Result<()> {
+    let log = PlayerLog::from_file("tests/fixtures/Player-match.log")?;
+    let game = |game_number, on_the_play, opponent_colors: &str, result| ArenaGame {
+        match_id: "c1d2e3f4".to_owned(),
+        game_number,
+        deck_id: Some("5e1b6d3a-0c2f-4d8e-9a41-2f7c8b0e6d11".to_owned()),
+        deck_cards: vec![67330, 67330, 67330, 69108, 69108],
+        on_the_play: Some(on_the_play),
+        opponent_colors: opponent_colors.to_owned(),
+        result,
+    };
+    assert_eq!(
+        log.games,
+        vec![
+            game(1, false, "UB", GameResult::Win),
+            game(2, true, "U", GameResult::Loss),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn submitted_deck_is_kept_for_one_match() -> Result<()> {
+    let log = fs::read_to_string("tests/fixtures/Player-match.log")?;
+    // The same match again, but without submitting a deck first
+    let next_match = log
+        .lines()
+        .skip(1)
+        .map(|line| line.replace("c1d2e3f4", "a9b8c7d6"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let log = PlayerLog::parse(&format!("{log}\n{next_match}"))?;
+    let deck_ids = log
+        .games
+        .iter()
+        .map(|game| game.deck_id.is_some())
+        .collect::<Vec<_>>();
+    assert_eq!(deck_ids, [true, true, false, false]);
+    Ok(())
+}
+
+#[test]
+fn games_are_attributed_by_link_or_cards() -> Result<()> {
+    let roster_file = NamedTempFile::new("test_roster.json")?;
+    fs::write(&roster_file, "[]")?;
+    let mut roster = Roster::open(&roster_file)?;
+    let burn = vec![(20, "Mountain".to_owned()), (4, "Shock".to_owned())];
+    roster.add_deck(arena_deck("Burn", "burn-id", burn.clone()));
+    let mut fable: Deck = "Deck\n20 Mountain\n4 Fable of the Mirror-Breaker\n".parse()?;
+    fable = fable.name("Fable");
+    roster.add_deck(fable);
+
+    let attributed = |id, main: &[(u8, String)]| {
+        match_history::attribute(&roster, id, main).map(|deck| deck.name.clone())
+    };
+    let fables = vec![(4, "Fable of the Mirror-Breaker".to_owned())];
+    assert_eq!(
+        attributed(Some("burn-id"), &fables),
+        Some("Burn".to_owned())
+    );
+    assert_eq!(attributed(None, &fables), Some("Fable".to_owned()));
+    assert_eq!(attributed(Some("other-id"), &burn), Some("Burn".to_owned()));
+    let elves = vec![(4, "Llanowar Elves".to_owned()), (2, "Mountain".to_owned())];
+    assert_eq!(attributed(None, &elves), None);
+    Ok(())
+}
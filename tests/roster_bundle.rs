@@ -0,0 +1,110 @@
+use anyhow::Result;
+use assert_fs::NamedTempFile;
+use detr::{
+    bundle::{MergeOutcome, OnConflict, RosterBundle},
+    Deck, Roster,
+};
+use std::fs;
+
+fn deck(name: &str, list: &str) -> Deck {
+    list.parse::<Deck>().unwrap().name(name)
+}
+
+fn roster(temp_file: &NamedTempFile) -> Result<Roster> {
+    fs::write(temp_file, "[]")?;
+    let mut roster = Roster::open(temp_file)?;
+    roster.add_deck(deck("Burn", "Deck\n20 Mountain\n4 Shock\n"));
+    roster.add_deck(deck(
+        "Fable",
+        "Deck\n20 Mountain\n4 Fable of the Mirror-Breaker\n",
+    ));
+    Ok(roster)
+}
+
+#[test]
+fn bundle_round_trip() -> Result<()> {
+    let roster_file = NamedTempFile::new("test_roster.json")?;
+    let bundle_file = NamedTempFile::new("test_bundle.json")?;
+    let mut roster = roster(&roster_file)?;
+    roster.find_mut("Burn")?.add_tag("standard");
+    RosterBundle::new(roster.decks().cloned().collect()).write(&bundle_file)?;
+
+    let bundle = RosterBundle::from_file(&bundle_file)?;
+    assert_eq!(bundle.decks, roster.decks().cloned().collect::<Vec<_>>());
+    assert!(bundle.decks[0].has_tag("standard"));
+    Ok(())
+}
+
+#[test]
+fn merge_handles_conflicts() -> Result<()> {
+    let roster_file = NamedTempFile::new("test_roster.json")?;
+    let mut roster = roster(&roster_file)?;
+    let imported = vec![
+        // Same list in a different order, and under another name
+        deck("Red deck wins", "Deck\n4 Shock\n20 Mountain\n"),
+        deck("Fable", "Deck\n24 Mountain\n"),
+        deck("Jund", "Deck\n4 Bloodbraid Elf\n"),
+    ];
+
+    let summary = roster.merge(imported.clone(), OnConflict::Skip);
+    assert_eq!(
+        summary.outcomes,
+        vec![
+            MergeOutcome::Identical {
+                imported: "Red deck wins".to_owned(),
+                local: "Burn".to_owned()
+            },
+            MergeOutcome::Skipped("Fable".to_owned()),
+            MergeOutcome::Added("Jund".to_owned()),
+        ]
+    );
+    assert_eq!(summary.changed(), 1);
+
+    let summary = roster.merge(imported[1..2].to_vec(), OnConflict::Rename);
+    assert_eq!(
+        summary.outcomes,
+        vec![MergeOutcome::Renamed {
+            imported: "Fable".to_owned(),
+            renamed: "Fable (2)".to_owned()
+        }]
+    );
+
+    let summary = roster.merge(
+        vec![deck("Burn", "Deck\n22 Mountain\n")],
+        OnConflict::Replace,
+    );
+    assert_eq!(
+        summary.outcomes,
+        vec![MergeOutcome::Replaced("Burn".to_owned())]
+    );
+    assert!(roster
+        .find("Burn")?
+        .same_list(&deck("", "Deck\n22 Mountain\n")));
+    assert_eq!(roster.len(), 4);
+    Ok(())
+}
+
+#[test]
+fn replaced_decks_keep_their_metadata() -> Result<()> {
+    let roster_file = NamedTempFile::new("test_roster.json")?;
+    let mut roster = roster(&roster_file)?;
+    let mut synced = Deck::from_parts(
+        "Synced",
+        None,
+        vec![(24, "Mountain".to_owned())],
+        Vec::new(),
+        Some("arena-deck-id".to_owned()),
+    );
+    synced.add_tag("standard");
+    roster.add_deck(synced);
+
+    let mut imported = deck("Synced", "Deck\n22 Mountain\n2 Shock\n");
+    imported.add_tag("aggro");
+    roster.merge(vec![imported], OnConflict::Replace);
+    let replaced = roster.find("Synced")?;
+    assert!(replaced.same_list(&deck("", "Deck\n22 Mountain\n2 Shock\n")));
+    assert_eq!(replaced.arena_id(), Some("arena-deck-id"));
+    assert!(replaced.has_tag("standard"));
+    assert!(replaced.has_tag("aggro"));
+    Ok(())
+}
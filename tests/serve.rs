@@ -0,0 +1,154 @@
+use anyhow::Result;
+use assert_fs::NamedTempFile;
+use detr::{config::Config, server::Api, Deck, Inventory, Roster};
+use reqwest::{blocking::Client, StatusCode};
+use serde_json::{json, Value};
+use std::fs;
+
+const COLLECTION: &str = r#"{"content": {
+    "Mountain": [[0, "land", "ltr"]],
+    "Lightning Strike": [[4, "common", "m19"]],
+    "Play with Fire": [[1, "uncommon", "mid"]],
+    "Fable of the Mirror-Breaker": [[0, "rare", "neo"]]
+}}"#;
+
+#[test]
+fn serve_roster_over_http() -> Result<()> {
+    let roster_file = NamedTempFile::new("test_roster.json")?;
+    let collection_file = NamedTempFile::new("test_collection.json")?;
+    let wildcards_file = NamedTempFile::new("test_wildcards.json")?;
+    fs::write(&roster_file, "[]")?;
+    fs::write(&collection_file, COLLECTION)?;
+    fs::write(
+        &wildcards_file,
+        r#"{"common": 20, "uncommon": 10, "rare": 4, "mythic": 1}"#,
+    )?;
+    let mut roster = Roster::open(&roster_file)?;
+    let mut inventory = Inventory::open(collection_file.path(), wildcards_file.path())?;
+    let config = Config::default();
+
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+    std::thread::scope(|scope| {
+        scope.spawn(|| Api::new(&mut roster, &mut inventory, &config, false).serve(&server));
+        let _stop = StopOnDrop(&server);
+        exercise_api(&url)
+    })
+}
+
+#[test]
+fn recommendations_fail_on_unknown_cards() -> Result<()> {
+    let roster_file = NamedTempFile::new("test_roster.json")?;
+    let collection_file = NamedTempFile::new("test_collection.json")?;
+    let wildcards_file = NamedTempFile::new("test_wildcards.json")?;
+    // Decks synced before the collection was updated can play cards it does
+    // not know of yet
+    let deck: Deck = "Deck\n20 Mountain\n4 Unheard of Dragon\n".parse()?;
+    fs::write(
+        &roster_file,
+        serde_json::to_string(&[deck.name("Dragons")])?,
+    )?;
+    fs::write(&collection_file, COLLECTION)?;
+    fs::write(
+        &wildcards_file,
+        r#"{"common": 20, "uncommon": 10, "rare": 4, "mythic": 1}"#,
+    )?;
+    let mut roster = Roster::open(&roster_file)?;
+    let mut inventory = Inventory::open(collection_file.path(), wildcards_file.path())?;
+    let config = Config::default();
+
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+    std::thread::scope(|scope| {
+        scope.spawn(|| Api::new(&mut roster, &mut inventory, &config, false).serve(&server));
+        let _stop = StopOnDrop(&server);
+        let client = Client::new();
+        let response = client
+            .post(format!("{url}/decks"))
+            .json(&json!({
+                "name": "More Dragons",
+                "decklist": "Deck\n20 Mountain\n4 Unheard of Dragon\n",
+            }))
+            .send()?;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = client
+            .get(format!("{url}/recommendations?rares=4&mythics=1"))
+            .send()?;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let error: Value = response.json()?;
+        let message = error["error"].as_str().unwrap();
+        assert!(message.contains("`Dragons`"));
+        assert!(message.contains("Unheard of Dragon"));
+
+        // The server is still up
+        let decks: Value = client.get(format!("{url}/decks")).send()?.json()?;
+        assert_eq!(decks[0]["name"], "Dragons");
+        Ok(())
+    })
+}
+
+/// Stops the server even when an assertion fails, so that the test does not
+/// hang waiting for it
+struct StopOnDrop<'s>(&'s tiny_http::Server);
+
+impl Drop for StopOnDrop<'_> {
+    fn drop(&mut self) {
+        self.0.unblock();
+    }
+}
+
+fn exercise_api(url: &str) -> Result<()> {
+    let client = Client::new();
+    let response = client
+        .post(format!("{url}/decks"))
+        .json(&json!({
+            "name": "Mono Red",
+            "decklist": "Deck\n20 Mountain\n4 lightning strike\n4 Play with Fire\n",
+            "tags": ["standard"],
+        }))
+        .send()?;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let deck: Value = response.json()?;
+    assert_eq!(deck["tags"], json!(["standard"]));
+    assert!(deck["decklist"]
+        .as_str()
+        .unwrap()
+        .contains("4 Lightning Strike"));
+
+    let response = client
+        .post(format!("{url}/decks"))
+        .json(&json!({ "name": "Mono Red", "decklist": "Deck\n20 Mountain\n" }))
+        .send()?;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let missing: Value = client
+        .get(format!("{url}/decks/Mono%20Red/missing"))
+        .send()?
+        .json()?;
+    assert_eq!(
+        missing,
+        json!([{"card": "Play with Fire", "amount": 3, "rarity": "uncommon", "set": "mid"}])
+    );
+
+    let response = client
+        .put(format!("{url}/decks/Mono%20Red"))
+        .json(&json!({ "decklist": "Deck\n20 Mountain\n4 Fable of the Mirror-Breaker\n" }))
+        .send()?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let suggestions: Value = client
+        .get(format!("{url}/suggestions?rarity=r&top=5"))
+        .send()?
+        .json()?;
+    assert_eq!(suggestions[0]["card"], "Fable of the Mirror-Breaker");
+    assert_eq!(suggestions[0]["share"], 100.0);
+
+    let response = client.delete(format!("{url}/decks/Mono%20Red")).send()?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get(format!("{url}/decks/Mono%20Red")).send()?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let decks: Value = client.get(format!("{url}/decks")).send()?.json()?;
+    assert_eq!(decks, json!([]));
+
+    Ok(())
+}
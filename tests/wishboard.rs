@@ -0,0 +1,52 @@
+use anyhow::Result;
+use detr::{Deck, Sideboard, WishCards};
+use std::collections::{BTreeSet, HashMap};
+
+fn cards(deck: &Deck, ignore_sideboard: bool) -> HashMap<String, u8> {
+    deck.cards(Sideboard::new(ignore_sideboard, &WishCards::default()))
+        .map(|(name, amount)| (name.clone(), amount))
+        .collect()
+}
+
+#[test]
+fn wish_fetches_seven_sideboard_cards() -> Result<()> {
+    // Rest in Peace, Leyline of Sanctity and Jace make 7 cards in 3 entries
+    let deck = Deck::from_file("boros_turns.txt")?;
+    let cards = cards(&deck, true);
+    assert_eq!(cards["Rest in Peace"], 2);
+    assert_eq!(cards["Leyline of Sanctity"], 4);
+    assert_eq!(cards["Jace, Wielder of Mysteries"], 1);
+    assert!(!cards.contains_key("Chandra, Awakened Inferno"));
+    Ok(())
+}
+
+#[test]
+fn wishboard_is_kept_apart() -> Result<()> {
+    let list = "Deck\n1 Wish\n20 Mountain\n\nSideboard\n3 Abrade\n\nWishboard\n1 Fiery Confluence\n\n1 Shock\n";
+    let deck: Deck = list.parse()?;
+    assert_eq!(deck.to_string().parse::<Deck>()?, deck);
+    let ignored = cards(&deck, true);
+    assert_eq!(ignored["Fiery Confluence"], 1);
+    assert_eq!(ignored["Shock"], 1);
+    assert!(!ignored.contains_key("Abrade"));
+    assert_eq!(cards(&deck, false)["Abrade"], 3);
+    Ok(())
+}
+
+#[test]
+fn configured_wishes_fetch_from_the_sideboard() -> Result<()> {
+    let deck = Deck::from_file("boros_turns.txt")?;
+    let default = WishCards::default();
+    let ignored = Sideboard::Ignored(&default);
+    // Jace is the 7th card of the sideboard, Chandra comes after it
+    assert!(deck.contains(&"Jace, Wielder of Mysteries", ignored));
+    assert!(!deck.contains(&"Chandra, Awakened Inferno", ignored));
+    assert!(deck.contains(&"Chandra, Awakened Inferno", Sideboard::Counted));
+
+    let no_wishes = WishCards(BTreeSet::new());
+    assert!(!deck.contains(&"Rest in Peace", Sideboard::Ignored(&no_wishes)));
+    assert!(deck
+        .cards(Sideboard::Ignored(&no_wishes))
+        .all(|(name, _)| name != "Rest in Peace"));
+    Ok(())
+}